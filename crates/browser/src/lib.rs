@@ -2,7 +2,7 @@
 #![deny(unsafe_code)]
 
 use futures::stream::TryStreamExt;
-use tower_lsp::{LspService, Server};
+use tower_lsp::Server;
 use wasm_bindgen::{prelude::*, JsCast};
 use wasm_bindgen_futures::stream::JsStream;
 
@@ -23,6 +23,36 @@ impl ServerConfig {
     }
 }
 
+/// Shows the syntax tree of the last parsed document in the `channel-syntax` text area of the page,
+/// colored by whether it has errors.
+struct ChannelSyntax;
+
+impl ChannelSyntax {
+    fn element() -> Option<web_sys::HtmlTextAreaElement> {
+        let element = web_sys::window()?.document()?.get_element_by_id("channel-syntax")?;
+        Some(element.unchecked_into())
+    }
+}
+
+#[tower_lsp::async_trait]
+impl demo_lsp_server::SyntaxTreeSink for ChannelSyntax {
+    async fn update(&self, tree: &demo_lsp_server::SyntaxTree) {
+        // NOTE: the page is not reachable from a Web Worker, in which case there is nothing to update.
+        if let Some(channel_syntax) = Self::element() {
+            channel_syntax.set_value(&tree.sexp);
+            let color = if tree.has_errors {
+                "rgb(255, 87, 51)"
+            } else {
+                "rgb(218, 247, 166)"
+            };
+            channel_syntax
+                .style()
+                .set_property("background-color", color)
+                .expect("failed to set style");
+        }
+    }
+}
+
 // NOTE: we don't use web_sys::ReadableStream for input here because on the
 // browser side we need to use a ReadableByteStreamController to construct it
 // and so far only Chromium-based browsers support that functionality.
@@ -57,7 +87,7 @@ pub async fn serve(config: ServerConfig) -> Result<(), JsValue> {
     let output = wasm_streams::WritableStream::from_raw(output);
     let output = output.try_into_async_write().map_err(|err| err.0)?;
 
-    let (service, messages) = LspService::new(|client| demo_lsp_server::Server::new(client, language));
+    let (service, messages) = demo_lsp_server::Server::service(language, Some(Box::new(ChannelSyntax)));
     Server::new(input, output, messages).serve(service).await;
 
     Ok(())
@@ -1,3 +1,4 @@
+#[cfg(feature = "wasm")]
 pub async fn javascript() -> anyhow::Result<tree_sitter::Language> {
     let bytes: &[u8] = include_bytes!("../../../node_modules/tree-sitter-javascript/tree-sitter-javascript.wasm");
     let result = web_tree_sitter_sys::Language::load_bytes(&bytes.into())
@@ -7,4 +8,9 @@ pub async fn javascript() -> anyhow::Result<tree_sitter::Language> {
     Ok(result)
 }
 
+#[cfg(feature = "native")]
+pub async fn javascript() -> anyhow::Result<tree_sitter::Language> {
+    Ok(tree_sitter_javascript::language().into())
+}
+
 pub static ID: &str = "javascript";
@@ -1,5 +1,8 @@
 #![deny(clippy::all)]
 #![deny(unsafe_code)]
 
+#[cfg(all(feature = "wasm", feature = "native"))]
+compile_error!("the `wasm` and `native` features are mutually exclusive");
+
 pub mod language;
 pub mod parser;
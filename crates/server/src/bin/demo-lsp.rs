@@ -0,0 +1,17 @@
+#![deny(clippy::all)]
+#![deny(unsafe_code)]
+
+use tower_lsp::Server;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let language = demo_lsp_language::language::javascript().await?;
+
+    let input = tokio::io::stdin();
+    let output = tokio::io::stdout();
+
+    let (service, messages) = demo_lsp_server::Server::service(language, None);
+    Server::new(input, output, messages).serve(service).await;
+
+    Ok(())
+}
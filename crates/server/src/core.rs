@@ -1,7 +1,13 @@
+pub mod declaration;
 pub mod document;
 pub mod error;
+pub mod query;
+pub mod scope;
 pub mod session;
+pub mod settings;
 pub mod syntax;
+#[cfg(all(test, feature = "native"))]
+pub mod test;
 pub mod text;
 
 pub use demo_lsp_language::{language, parser};
@@ -0,0 +1,506 @@
+use crate::core::scope::{Binding, BindingKind, Scopes};
+use lsp_text::RopeExt;
+
+/// A parsed JSDoc comment.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct JsDoc {
+    pub description: String,
+    /// The `@param` tags as pairs of parameter name and description.
+    pub params: Vec<(String, String)>,
+    pub returns: Option<String>,
+    /// Every other block tag as pairs of tag name (without the `@`) and text.
+    pub tags: Vec<(String, String)>,
+}
+
+impl JsDoc {
+    /// Parse the text of a `/** ... */` comment. Returns [`None`] for other comments.
+    pub fn parse(comment: &str) -> Option<Self> {
+        let body = comment.strip_prefix("/**")?.strip_suffix("*/")?;
+
+        let lines = body.lines().map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        });
+
+        // NOTE: each block tag extends until the next line starting with a tag.
+        let mut description = vec![];
+        let mut blocks: Vec<(String, Vec<&str>)> = vec![];
+        for line in lines {
+            if let Some(tag) = line.strip_prefix('@') {
+                let (name, rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+                blocks.push((name.to_string(), vec![rest.trim()]));
+            } else if let Some((_, block)) = blocks.last_mut() {
+                block.push(line.trim_start());
+            } else {
+                description.push(line);
+            }
+        }
+
+        let mut doc = JsDoc {
+            description: join_lines(&description),
+            ..Default::default()
+        };
+        for (tag, block) in blocks {
+            let text = join_lines(&block);
+            match tag.as_str() {
+                "param" | "arg" | "argument" => {
+                    if let Some(param) = parse_param(&text) {
+                        doc.params.push(param);
+                    }
+                },
+                "returns" | "return" => doc.returns = Some(strip_type(&text).to_string()),
+                _ => doc.tags.push((tag, text)),
+            }
+        }
+        Some(doc)
+    }
+
+    /// Return the description of the named parameter, if documented.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, description)| description.as_str())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut sections = vec![];
+        if !self.description.is_empty() {
+            sections.push(self.description.clone());
+        }
+        for (name, description) in &self.params {
+            sections.push(format!("_@param_ `{}` — {}", name, description));
+        }
+        if let Some(returns) = &self.returns {
+            sections.push(format!("_@returns_ — {}", returns));
+        }
+        for (tag, text) in &self.tags {
+            sections.push(format!("_@{}_ {}", tag, text));
+        }
+        sections.join("\n\n")
+    }
+
+    pub fn to_plaintext(&self) -> String {
+        let mut sections = vec![];
+        if !self.description.is_empty() {
+            sections.push(self.description.clone());
+        }
+        for (name, description) in &self.params {
+            sections.push(format!("@param {} - {}", name, description));
+        }
+        if let Some(returns) = &self.returns {
+            sections.push(format!("@returns {}", returns));
+        }
+        for (tag, text) in &self.tags {
+            sections.push(format!("@{} {}", tag, text));
+        }
+        sections.join("\n")
+    }
+}
+
+fn join_lines(lines: &[&str]) -> String {
+    lines.join("\n").trim().to_string()
+}
+
+/// Strip a leading `{type}` annotation.
+fn strip_type(text: &str) -> &str {
+    if text.starts_with('{') {
+        let mut depth = 0;
+        for (i, c) in text.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return text[i + 1 ..].trim_start();
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+    text
+}
+
+/// Parse the text of a `@param` tag, e.g., `{number} [count=1] - the number of items`.
+fn parse_param(text: &str) -> Option<(String, String)> {
+    let text = strip_type(text);
+    let (name, description) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    let name = name.split('=').next().unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+    let description = description.trim_start();
+    let description = description.strip_prefix('-').unwrap_or(description).trim();
+    Some((name.to_string(), description.to_string()))
+}
+
+/// Collapse the whitespace in the text so that it fits on a single line.
+pub fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Find the node declaring the binding.
+pub fn node<'tree>(tree: &'tree tree_sitter::Tree, binding: &Binding) -> Option<tree_sitter::Node<'tree>> {
+    let span = &binding.declaration;
+    let mut node = tree.root_node().named_descendant_for_byte_range(span.start, span.end)?;
+    // NOTE: the smallest node spanning the range may be a child which happens to span all of it.
+    while node.start_byte() != span.start || node.end_byte() != span.end {
+        node = node.parent()?;
+    }
+    while let Some(parent) = node.parent() {
+        if parent.start_byte() != span.start || parent.end_byte() != span.end {
+            break;
+        }
+        node = parent;
+    }
+    Some(node)
+}
+
+/// Whether the node is a function, arrow function or method.
+pub fn is_function(node: &tree_sitter::Node) -> bool {
+    matches!(
+        &*node.kind(),
+        "function_declaration"
+            | "generator_function_declaration"
+            | "function"
+            | "generator_function"
+            | "arrow_function"
+            | "method_definition"
+    )
+}
+
+/// Return the parameter nodes of a function-like node.
+pub fn parameters<'tree>(node: &tree_sitter::Node<'tree>) -> Vec<tree_sitter::Node<'tree>> {
+    if let Some(parameters) = node.child_by_field_name("parameters") {
+        (0 .. parameters.named_child_count())
+            .filter_map(|i| parameters.named_child(i))
+            .filter(|parameter| parameter.kind() != "comment")
+            .collect()
+    } else {
+        node.child_by_field_name("parameter").into_iter().collect()
+    }
+}
+
+/// Return the name of a parameter, if it is not a destructuring pattern.
+pub fn parameter_name(content: &ropey::Rope, parameter: &tree_sitter::Node) -> Option<String> {
+    match &*parameter.kind() {
+        "identifier" => Some(content.utf8_text_for_tree_sitter_node(parameter).to_string()),
+        "assignment_pattern" => parameter
+            .child_by_field_name("left")
+            .and_then(|left| parameter_name(content, &left)),
+        "rest_pattern" => parameter
+            .named_child(0)
+            .and_then(|pattern| parameter_name(content, &pattern))
+            .map(|name| format!("...{}", name)),
+        _ => None,
+    }
+}
+
+/// A function resolved from the callee of a call.
+pub struct Callee<'tree> {
+    pub name: String,
+    /// The function-like node, e.g., the `arrow_function` or the constructor `method_definition`.
+    pub function: tree_sitter::Node<'tree>,
+    /// The node which a JSDoc comment for the function would precede.
+    pub declaration: tree_sitter::Node<'tree>,
+}
+
+/// Resolve the callee of a call (or `new`) expression to a function declared in the document.
+pub fn callee<'tree>(
+    content: &ropey::Rope,
+    tree: &'tree tree_sitter::Tree,
+    scopes: &Scopes,
+    callee: &tree_sitter::Node<'tree>,
+) -> Option<Callee<'tree>> {
+    let text = |node: &tree_sitter::Node| content.utf8_text_for_tree_sitter_node(node).to_string();
+    match &*callee.kind() {
+        "identifier" => {
+            let binding = &scopes.bindings[scopes.binding_at(callee.start_byte())?];
+            let declaration = node(tree, binding)?;
+            let name = binding.name.clone();
+            match &*declaration.kind() {
+                _ if is_function(&declaration) => Some(Callee {
+                    name,
+                    function: declaration.clone(),
+                    declaration,
+                }),
+                "variable_declarator" => {
+                    let value = declaration.child_by_field_name("value").filter(is_function)?;
+                    Some(Callee {
+                        name,
+                        function: value,
+                        declaration,
+                    })
+                },
+                "class_declaration" => {
+                    let function = method(&declaration.child_by_field_name("body")?, content, "constructor")?;
+                    Some(Callee {
+                        name,
+                        function: function.clone(),
+                        declaration: function,
+                    })
+                },
+                _ => None,
+            }
+        },
+        "member_expression" => {
+            let property = callee.child_by_field_name("property")?;
+            let name = text(&property);
+            let object = callee.child_by_field_name("object")?;
+            // NOTE: prefer the methods of the enclosing class for calls on `this`.
+            let class_body = std::iter::successors(Some(callee.clone()), |node| node.parent())
+                .find(|node| node.kind() == "class_body")
+                .filter(|_| object.kind() == "this");
+            let function = class_body
+                .and_then(|class_body| method(&class_body, content, &name))
+                .or_else(|| {
+                    methods(tree)
+                        .into_iter()
+                        .find(|method| method_name(content, method).as_deref() == Some(name.as_str()))
+                })?;
+            Some(Callee {
+                name,
+                function: function.clone(),
+                declaration: function,
+            })
+        },
+        _ => None,
+    }
+}
+
+fn method_name(content: &ropey::Rope, method: &tree_sitter::Node) -> Option<String> {
+    let name = method.child_by_field_name("name")?;
+    Some(content.utf8_text_for_tree_sitter_node(&name).to_string())
+}
+
+/// Find the method with the given name in a class body.
+fn method<'tree>(
+    class_body: &tree_sitter::Node<'tree>,
+    content: &ropey::Rope,
+    name: &str,
+) -> Option<tree_sitter::Node<'tree>> {
+    (0 .. class_body.named_child_count())
+        .filter_map(|i| class_body.named_child(i))
+        .filter(|member| member.kind() == "method_definition")
+        .find(|member| method_name(content, member).as_deref() == Some(name))
+}
+
+/// Collect the method definitions in the document.
+fn methods(tree: &tree_sitter::Tree) -> Vec<tree_sitter::Node> {
+    let mut methods = vec![];
+    let mut cursor = tree.walk();
+    'nodes: loop {
+        if cursor.node().kind() == "method_definition" {
+            methods.push(cursor.node());
+        }
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if cursor.goto_next_sibling() {
+                continue 'nodes;
+            }
+            if !cursor.goto_parent() {
+                break 'nodes;
+            }
+        }
+    }
+    methods
+}
+
+/// Format the signature of a function-like node, e.g., `async function foo(a, b = 1)`.
+pub fn function_signature(content: &ropey::Rope, node: &tree_sitter::Node) -> String {
+    let text = |node: &tree_sitter::Node| single_line(&content.utf8_text_for_tree_sitter_node(node));
+    let has_keyword = |keyword: &str| {
+        (0 .. node.child_count())
+            .filter_map(|i| node.child(i))
+            .any(|child| !child.is_named() && child.kind() == keyword)
+    };
+
+    let mut signature = String::new();
+    for keyword in ["static", "async", "get", "set"] {
+        if has_keyword(keyword) {
+            signature.push_str(keyword);
+            signature.push(' ');
+        }
+    }
+
+    let name = node.child_by_field_name("name").map(|name| text(&name));
+    let parameters = parameters(node).iter().map(text).collect::<Vec<_>>().join(", ");
+
+    match &*node.kind() {
+        "arrow_function" => {
+            signature.push_str(&format!("({}) => …", parameters));
+        },
+        "method_definition" => {
+            let generator = if has_keyword("*") { "*" } else { "" };
+            let name = name.unwrap_or_default();
+            signature.push_str(&format!("{}{}({})", generator, name, parameters));
+        },
+        kind => {
+            let keyword = if kind.starts_with("generator") {
+                "function*"
+            } else {
+                "function"
+            };
+            match name {
+                Some(name) => signature.push_str(&format!("{} {}({})", keyword, name, parameters)),
+                None => signature.push_str(&format!("{} ({})", keyword, parameters)),
+            }
+        },
+    }
+
+    signature
+}
+
+/// Format a one-line description of the binding, e.g., `const x = 42` or `class Foo extends Bar`.
+pub fn signature(content: &ropey::Rope, tree: &tree_sitter::Tree, binding: &Binding) -> String {
+    let text = |node: &tree_sitter::Node| single_line(&content.utf8_text_for_tree_sitter_node(node));
+    let name = &binding.name;
+    let identifier = tree
+        .root_node()
+        .named_descendant_for_byte_range(binding.span.start, binding.span.end);
+    let declaration = node(tree, binding);
+
+    match binding.kind {
+        BindingKind::Function => match &declaration {
+            Some(declaration) if is_function(declaration) => function_signature(content, declaration),
+            _ => format!("function {}", name),
+        },
+        BindingKind::Class => {
+            let heritage = declaration.as_ref().and_then(|declaration| {
+                (0 .. declaration.named_child_count())
+                    .filter_map(|i| declaration.named_child(i))
+                    .find(|child| child.kind() == "class_heritage")
+            });
+            match heritage {
+                Some(heritage) => format!("class {} {}", name, text(&heritage)),
+                None => format!("class {}", name),
+            }
+        },
+        BindingKind::Var | BindingKind::Let | BindingKind::Const => {
+            let keyword = binding.kind.keyword().unwrap_or_default();
+            let value = declaration
+                .as_ref()
+                .filter(|declaration| declaration.kind() == "variable_declarator")
+                .and_then(|declaration| declaration.child_by_field_name("value"));
+            match value {
+                Some(value) if is_function(&value) => {
+                    format!("{} {} = {}", keyword, name, function_signature(content, &value))
+                },
+                Some(value) if value.kind() == "class" => format!("{} {} = class", keyword, name),
+                Some(value) if is_short_literal(content, &value) => {
+                    format!("{} {} = {}", keyword, name, text(&value))
+                },
+                _ => format!("{} {}", keyword, name),
+            }
+        },
+        BindingKind::Parameter | BindingKind::CatchParameter => {
+            let label = if binding.kind == BindingKind::Parameter {
+                "(parameter)"
+            } else {
+                "(catch parameter)"
+            };
+            let default = identifier
+                .and_then(|identifier| identifier.parent())
+                .filter(|parent| parent.kind() == "assignment_pattern");
+            match default {
+                Some(default) => format!("{} {}", label, text(&default)),
+                None => format!("{} {}", label, name),
+            }
+        },
+        BindingKind::Import => {
+            let statement = declaration.and_then(|declaration| {
+                std::iter::successors(Some(declaration), |node| node.parent())
+                    .find(|node| node.kind() == "import_statement")
+            });
+            let source = statement
+                .and_then(|statement| statement.child_by_field_name("source"))
+                .map(|source| text(&source))
+                .unwrap_or_default();
+            let specifier = identifier.and_then(|identifier| identifier.parent());
+            match specifier {
+                Some(specifier) if specifier.kind() == "import_specifier" => {
+                    format!("import {{ {} }} from {}", text(&specifier), source)
+                },
+                Some(specifier) if specifier.kind() == "namespace_import" => {
+                    format!("import * as {} from {}", name, source)
+                },
+                _ => format!("import {} from {}", name, source),
+            }
+        },
+    }
+}
+
+fn is_short_literal(content: &ropey::Rope, node: &tree_sitter::Node) -> bool {
+    let is_literal = matches!(
+        &*node.kind(),
+        "number" | "string" | "true" | "false" | "null" | "undefined" | "regex"
+    );
+    is_literal && node.start_position().row() == node.end_position().row() && {
+        let text = content.utf8_text_for_tree_sitter_node(node);
+        text.chars().count() <= 40
+    }
+}
+
+/// Return the JSDoc comment immediately preceding the declaration, if any.
+pub fn documentation(content: &ropey::Rope, declaration: &tree_sitter::Node) -> Option<JsDoc> {
+    // NOTE: the comment precedes the whole statement, e.g., `/** ... */ export const x = 1, y;`.
+    let mut statement = declaration.clone();
+    while let Some(parent) = statement.parent() {
+        let is_wrapper = matches!(
+            &*parent.kind(),
+            "lexical_declaration" | "variable_declaration" | "export_statement"
+        );
+        if !is_wrapper {
+            break;
+        }
+        statement = parent;
+    }
+
+    let comment = statement.prev_named_sibling().filter(|sibling| sibling.kind() == "comment")?;
+    if comment.end_position().row() + 1 < statement.start_position().row() {
+        return None;
+    }
+    JsDoc::parse(&content.utf8_text_for_tree_sitter_node(&comment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsDoc;
+
+    #[test]
+    fn parse_jsdoc() {
+        let comment = indoc::indoc! {r"
+            /**
+             * Add two numbers.
+             *
+             * @param {number} a - the first number
+             * @param {number} [b=1] the second
+             *   number
+             * @returns {number} the sum
+             * @deprecated
+             */"};
+        let expected = JsDoc {
+            description: "Add two numbers.".into(),
+            params: vec![
+                ("a".into(), "the first number".into()),
+                ("b".into(), "the second\nnumber".into()),
+            ],
+            returns: Some("the sum".into()),
+            tags: vec![("deprecated".into(), "".into())],
+        };
+        assert_eq!(Some(expected), JsDoc::parse(comment.trim()));
+    }
+
+    #[test]
+    fn parse_not_jsdoc() {
+        assert_eq!(None, JsDoc::parse("/* plain */"));
+        assert_eq!(None, JsDoc::parse("// line"));
+    }
+}
@@ -1,4 +1,3 @@
-use async_lock::Mutex;
 use lsp_text::RopeExt;
 use std::sync::Arc;
 
@@ -22,33 +21,9 @@ impl Document {
             let old_tree = None;
             parser.parse_with(callback, old_tree)?
         };
-        crate::core::syntax::update_channel(result.as_ref());
         Ok(result.map(|tree| crate::core::Document { content, parser, tree }))
     }
 
-    pub async fn change<'changes>(
-        session: Arc<crate::core::Session>,
-        uri: &lsp::Url,
-        content: &ropey::Rope,
-    ) -> anyhow::Result<Option<tree_sitter::Tree>> {
-        let result = {
-            let parser = session.get_mut_parser(uri).await?;
-            let mut parser = parser.lock().await;
-            let text = content.chunks().collect::<String>();
-            parser.parse(text, None)?
-        };
-        crate::core::syntax::update_channel(result.as_ref());
-        if let Some(tree) = result {
-            {
-                let tree = tree.clone();
-                *session.get_mut_tree(uri).await?.value_mut() = Mutex::new(tree);
-            }
-            Ok(Some(tree))
-        } else {
-            Ok(None)
-        }
-    }
-
     /// Return the language-id and textual content portion of the [`Document`].
     pub fn text(&self) -> crate::core::Text {
         crate::core::Text {
@@ -57,8 +32,65 @@ impl Document {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
-pub enum DocumentState {
-    Closed,
-    Opened,
+/// The state of an open document in the session.
+///
+/// NOTE: the text and tree always belong to the same version of the document since an entry is
+/// only ever inserted, changed or removed as a whole while the session holds it exclusively, which
+/// is also what gives [`DocumentEntry::change`] exclusive use of the parser.
+pub struct DocumentEntry {
+    pub text: crate::core::Text,
+    pub parser: tree_sitter::Parser,
+    pub tree: tree_sitter::Tree,
+    pub version: i32,
+    pub language_id: String,
+}
+
+impl DocumentEntry {
+    pub fn new(document: Document, version: i32, language_id: String) -> Self {
+        DocumentEntry {
+            text: document.text(),
+            parser: document.parser,
+            tree: document.tree,
+            version,
+            language_id,
+        }
+    }
+
+    /// Apply the changes of a `textDocument/didChange` notification and reparse the document.
+    ///
+    /// The entry is only updated if every change applies and the document parses; otherwise it is
+    /// left as it was. Returns [`None`] if the document could not be parsed.
+    pub fn change(
+        &mut self,
+        version: i32,
+        changes: &[lsp::TextDocumentContentChangeEvent],
+    ) -> anyhow::Result<Option<tree_sitter::Tree>> {
+        let mut content = self.text.content.clone();
+        let mut old_tree = self.tree.clone();
+
+        // NOTE: each change is relative to the content resulting from the previous change, so the
+        // edits must be built and applied one at a time rather than all at once. The old tree must
+        // be edited before reparsing so that tree-sitter can reuse the unchanged subtrees.
+        for change in changes {
+            let edit = content.build_edit(change)?;
+            content.apply_edit(&edit);
+            old_tree.edit(&edit.input_edit);
+        }
+
+        let result = {
+            let content = content.clone();
+            let byte_idx = 0;
+            let callback = content.chunk_walker(byte_idx).callback_adapter_for_tree_sitter();
+            self.parser.parse_with(callback, Some(&old_tree))?
+        };
+
+        if let Some(tree) = &result {
+            self.text.content = content;
+            self.tree = tree.clone();
+            self.version = version;
+        }
+
+        Ok(result)
+    }
 }
+
@@ -6,6 +6,18 @@ use thiserror::Error;
 pub enum Error {
     #[error("ClientNotInitialzed")]
     ClientNotInitialized,
+    #[error("core::ContentModified: uri={uri:?}")]
+    ContentModified { uri: lsp::Url },
+    #[error("invalid query at byte {offset}: {message}")]
+    InvalidQuery { offset: usize, message: String },
+    #[error("`{name}` is not a valid identifier")]
+    InvalidIdentifier { name: String },
+    #[error("renaming to `{name}` would conflict with an existing binding")]
+    RenameConflict { name: String },
+    #[error("`{name}` is not declared in this document and cannot be renamed")]
+    RenameUnresolved { name: String },
+    #[error("`{name}` is a reserved word")]
+    ReservedWord { name: String },
     #[error("core::SessionResourceNotFound: kind={kind:?}, uri={uri:?}")]
     SessionResourceNotFound {
         kind: core::session::SessionResourceKind,
@@ -17,9 +29,24 @@ pub struct IntoJsonRpcError(pub anyhow::Error);
 
 impl From<IntoJsonRpcError> for tower_lsp::jsonrpc::Error {
     fn from(error: IntoJsonRpcError) -> Self {
-        let mut rpc_error = tower_lsp::jsonrpc::Error::internal_error();
-        rpc_error.data = Some(serde_json::to_value(format!("{}", error.0)).unwrap());
-        rpc_error
+        // NOTE: errors caused by invalid input from the user are reported with their message so
+        // that the client can display them.
+        match error.0.downcast_ref::<Error>() {
+            Some(error @ Error::InvalidIdentifier { .. })
+            | Some(error @ Error::RenameConflict { .. })
+            | Some(error @ Error::RenameUnresolved { .. })
+            | Some(error @ Error::ReservedWord { .. }) => tower_lsp::jsonrpc::Error::invalid_params(error.to_string()),
+            // NOTE: results computed from a document which has since changed are dropped so that
+            // the client can ask again for the current version.
+            Some(Error::ContentModified { .. }) => {
+                tower_lsp::jsonrpc::Error::new(tower_lsp::jsonrpc::ErrorCode::ContentModified)
+            },
+            _ => {
+                let mut rpc_error = tower_lsp::jsonrpc::Error::internal_error();
+                rpc_error.data = Some(serde_json::to_value(format!("{}", error.0)).unwrap());
+                rpc_error
+            },
+        }
     }
 }
 
@@ -39,4 +66,21 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn from_invalid_params() {
+        let error = Error::ReservedWord { name: "class".into() };
+        let expected = tower_lsp::jsonrpc::Error::invalid_params(format!("{}", error));
+        let actual: tower_lsp::jsonrpc::Error = IntoJsonRpcError(error.into()).into();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn from_content_modified() {
+        let uri = lsp::Url::parse("file:///demo.js").unwrap();
+        let error = Error::ContentModified { uri };
+        let expected = tower_lsp::jsonrpc::Error::new(tower_lsp::jsonrpc::ErrorCode::ContentModified);
+        let actual: tower_lsp::jsonrpc::Error = IntoJsonRpcError(error.into()).into();
+        assert_eq!(expected, actual);
+    }
 }
@@ -0,0 +1,372 @@
+//! Tree-sitter queries, compiled and matched by the query engine of the backend.
+//!
+//! NOTE: tree-sitter-facade does not expose queries, so the facade language and nodes are converted
+//! to those of the backend (web-tree-sitter-sys or tree-sitter) here, and nowhere else. The facade
+//! only converts from the backend types, so the conversion in the other direction reinterprets the
+//! facade values, which are single-field wrappers around the backend values. This is only sound
+//! while both refer to the same backend crate, which is why the backends are pinned to the versions
+//! used by the facade in Cargo.toml, and while the layouts agree, which is asserted at compile time.
+
+/// A query written in the tree-sitter query language.
+///
+/// The text predicates `#eq?` and `#match?` (and their `#not-` forms) are evaluated by the backend;
+/// queries using any other predicate are rejected.
+pub struct Query {
+    inner: backend::Query,
+    capture_names: Vec<String>,
+}
+
+impl Query {
+    pub fn new(language: &tree_sitter::Language, source: &str) -> anyhow::Result<Self> {
+        let inner = backend::Query::new(language, source)?;
+        let capture_names = inner.capture_names();
+        Ok(Query { inner, capture_names })
+    }
+
+    /// The names of the captures, indexed by [`QueryCapture::index`].
+    pub fn capture_names(&self) -> &[String] {
+        &self.capture_names
+    }
+
+    pub fn capture_index_for_name(&self, name: &str) -> Option<u32> {
+        self.capture_names.iter().position(|other| other == name).map(|index| index as u32)
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.inner.pattern_count()
+    }
+}
+
+impl std::fmt::Debug for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Query")
+            .field("capture_names", &self.capture_names)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs queries over the nodes of a tree.
+#[derive(Clone, Debug, Default)]
+pub struct QueryCursor {}
+
+impl QueryCursor {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Return the matches of the query within the node. The content is the text of the tree, which
+    /// is used to evaluate predicates.
+    pub fn matches<'tree>(
+        &self,
+        query: &Query,
+        node: &tree_sitter::Node<'tree>,
+        content: &ropey::Rope,
+    ) -> Vec<QueryMatch<'tree>> {
+        backend::matches(&query.inner, node, content)
+    }
+}
+
+/// A match of one of the patterns of a query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryMatch<'tree> {
+    pub pattern_index: usize,
+    pub captures: Vec<QueryCapture<'tree>>,
+}
+
+impl<'tree> QueryMatch<'tree> {
+    /// Return the first node captured under the name.
+    pub fn capture(&self, query: &Query, name: &str) -> Option<&tree_sitter::Node<'tree>> {
+        let index = query.capture_index_for_name(name)?;
+        self.captures
+            .iter()
+            .find(|capture| capture.index == index)
+            .map(|capture| &capture.node)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryCapture<'tree> {
+    pub node: tree_sitter::Node<'tree>,
+    /// The index of the name of the capture in [`Query::capture_names`].
+    pub index: u32,
+}
+
+fn invalid(offset: usize, message: impl Into<String>) -> anyhow::Error {
+    let message = message.into();
+    crate::core::Error::InvalidQuery { offset, message }.into()
+}
+
+#[cfg(feature = "wasm")]
+#[allow(unsafe_code)]
+mod backend {
+    use super::{QueryCapture, QueryMatch};
+    use wasm_bindgen::JsCast;
+
+    // NOTE: the `Send` and `Sync` implementations below rely on there being a single thread.
+    #[cfg(target_feature = "atomics")]
+    compile_error!("the web-tree-sitter query backend does not support threads");
+
+    const _: () = {
+        use std::mem::{align_of, size_of};
+        assert!(size_of::<tree_sitter::Language>() == size_of::<web_tree_sitter_sys::Language>());
+        assert!(align_of::<tree_sitter::Language>() == align_of::<web_tree_sitter_sys::Language>());
+        assert!(size_of::<tree_sitter::Node>() == size_of::<web_tree_sitter_sys::SyntaxNode>());
+        assert!(align_of::<tree_sitter::Node>() == align_of::<web_tree_sitter_sys::SyntaxNode>());
+    };
+
+    pub struct Query {
+        inner: web_tree_sitter_sys::Query,
+    }
+
+    // SAFETY: the query is a handle to a JavaScript object, which can only be used from the thread
+    // which created it. Without the `atomics` target feature (rejected above), wasm has exactly one
+    // thread, so the query is never sent to or shared with another thread.
+    unsafe impl Send for Query {}
+
+    // SAFETY: as for `Send`, there is no other thread to share the query with.
+    unsafe impl Sync for Query {}
+
+    fn language(language: &tree_sitter::Language) -> web_tree_sitter_sys::Language {
+        // SAFETY: the facade language of the wasm backend is a struct whose only field is a
+        // `web_tree_sitter_sys::Language` of the same crate version (see the module documentation),
+        // with the same size and alignment (asserted above). The clone holds its own reference to
+        // the JavaScript object, whose ownership moves into the result, so it is released once.
+        unsafe { std::mem::transmute::<tree_sitter::Language, web_tree_sitter_sys::Language>(language.clone()) }
+    }
+
+    fn node(node: &tree_sitter::Node) -> web_tree_sitter_sys::SyntaxNode {
+        // SAFETY: as for `language`, the facade node is a struct whose only field is a
+        // `web_tree_sitter_sys::SyntaxNode`, and the clone's reference moves into the result. The
+        // JavaScript node keeps its tree alive, so the result does not borrow from the facade node.
+        unsafe { std::mem::transmute::<tree_sitter::Node, web_tree_sitter_sys::SyntaxNode>(node.clone()) }
+    }
+
+    impl Query {
+        pub fn new(language: &tree_sitter::Language, source: &str) -> anyhow::Result<Self> {
+            let inner = self::language(language).query(&source.into()).map_err(|error| {
+                let error = wasm_bindgen::JsValue::from(error);
+                let offset = property(&error, "index").as_f64().unwrap_or_default() as usize;
+                let message = error
+                    .dyn_ref::<js_sys::Error>()
+                    .map(|error| String::from(error.message()))
+                    .unwrap_or_else(|| "invalid query".into());
+                super::invalid(offset, message)
+            })?;
+
+            // NOTE: web-tree-sitter keeps the predicates it does not evaluate itself, per pattern.
+            for predicates in js_sys::Array::from(&property(inner.as_ref(), "predicates")).iter() {
+                let predicates = js_sys::Array::from(&predicates);
+                if predicates.length() > 0 {
+                    let operator = property(&predicates.get(0), "operator").as_string().unwrap_or_default();
+                    let message = format!("unsupported predicate `#{}`", operator);
+                    return Err(super::invalid(0, message));
+                }
+            }
+
+            Ok(Query { inner })
+        }
+
+        pub fn capture_names(&self) -> Vec<String> {
+            js_sys::Array::from(&property(self.inner.as_ref(), "captureNames"))
+                .iter()
+                .filter_map(|name| name.as_string())
+                .collect()
+        }
+
+        pub fn pattern_count(&self) -> usize {
+            js_sys::Array::from(&property(self.inner.as_ref(), "predicates")).length() as usize
+        }
+    }
+
+    pub fn matches<'tree>(
+        query: &Query,
+        node: &tree_sitter::Node<'tree>,
+        _content: &ropey::Rope,
+    ) -> Vec<QueryMatch<'tree>> {
+        // NOTE: web-tree-sitter reads the text for predicates through the tree, so the content is not
+        // needed here.
+
+        let node = self::node(node);
+        let capture_names = query.capture_names();
+        let start_position = None;
+        let end_position = None;
+        query
+            .inner
+            .matches(&node, start_position, end_position)
+            .into_vec()
+            .into_iter()
+            .map(JsCast::unchecked_into::<web_tree_sitter_sys::QueryMatch>)
+            .map(|r#match| {
+                let pattern_index = property(r#match.as_ref(), "pattern").as_f64().unwrap_or_default() as usize;
+                let captures = r#match
+                    .captures()
+                    .into_vec()
+                    .into_iter()
+                    .map(JsCast::unchecked_into::<web_tree_sitter_sys::QueryCapture>)
+                    .filter_map(|capture| {
+                        let name = String::from(capture.name());
+                        let index = capture_names.iter().position(|other| *other == name)? as u32;
+                        let node = capture.node().into();
+                        Some(QueryCapture { node, index })
+                    })
+                    .collect();
+                QueryMatch { pattern_index, captures }
+            })
+            .collect()
+    }
+
+    fn property(target: &wasm_bindgen::JsValue, key: &str) -> wasm_bindgen::JsValue {
+        js_sys::Reflect::get(target, &key.into()).unwrap_or(wasm_bindgen::JsValue::UNDEFINED)
+    }
+}
+
+#[cfg(feature = "native")]
+#[allow(unsafe_code)]
+mod backend {
+    use super::{QueryCapture, QueryMatch};
+
+    const _: () = {
+        use std::mem::{align_of, size_of};
+        assert!(size_of::<tree_sitter::Language>() == size_of::<tree_sitter_native::Language>());
+        assert!(align_of::<tree_sitter::Language>() == align_of::<tree_sitter_native::Language>());
+        assert!(size_of::<tree_sitter::Node>() == size_of::<tree_sitter_native::Node>());
+        assert!(align_of::<tree_sitter::Node>() == align_of::<tree_sitter_native::Node>());
+    };
+
+    pub struct Query {
+        inner: tree_sitter_native::Query,
+    }
+
+    fn language(language: &tree_sitter::Language) -> tree_sitter_native::Language {
+        // SAFETY: the facade language of the native backend is a struct whose only field is a
+        // `tree_sitter_native::Language` of the same crate version (see the module documentation),
+        // with the same size and alignment (asserted above). The language is a plain pointer to
+        // static data, so the copy owns nothing which could be released twice.
+        unsafe { std::mem::transmute::<tree_sitter::Language, tree_sitter_native::Language>(language.clone()) }
+    }
+
+    fn node<'tree>(node: &tree_sitter::Node<'tree>) -> tree_sitter_native::Node<'tree> {
+        // SAFETY: as for `language`, the facade node is a struct whose only field is a
+        // `tree_sitter_native::Node` with the same lifetime, so the result borrows the same tree for
+        // no longer than the facade node. Nodes own nothing which could be released twice.
+        unsafe { std::mem::transmute::<tree_sitter::Node<'tree>, tree_sitter_native::Node<'tree>>(node.clone()) }
+    }
+
+    impl Query {
+        pub fn new(language: &tree_sitter::Language, source: &str) -> anyhow::Result<Self> {
+            let inner = tree_sitter_native::Query::new(self::language(language), source)
+                .map_err(|error| super::invalid(error.offset, error.message))?;
+
+            // NOTE: tree-sitter keeps the predicates it does not evaluate itself, per pattern.
+            for pattern_index in 0 .. inner.pattern_count() {
+                if let Some(predicate) = inner.general_predicates(pattern_index).first() {
+                    let offset = inner.start_byte_for_pattern(pattern_index);
+                    let message = format!("unsupported predicate `#{}`", predicate.operator);
+                    return Err(super::invalid(offset, message));
+                }
+            }
+
+            Ok(Query { inner })
+        }
+
+        pub fn capture_names(&self) -> Vec<String> {
+            self.inner.capture_names().to_vec()
+        }
+
+        pub fn pattern_count(&self) -> usize {
+            self.inner.pattern_count()
+        }
+    }
+
+    pub fn matches<'tree>(
+        query: &Query,
+        node: &tree_sitter::Node<'tree>,
+        content: &ropey::Rope,
+    ) -> Vec<QueryMatch<'tree>> {
+        let node = self::node(node);
+        let text = move |node: tree_sitter_native::Node| {
+            content
+                .byte_slice(node.start_byte() .. node.end_byte())
+                .chunks()
+                .map(str::as_bytes)
+        };
+        let mut cursor = tree_sitter_native::QueryCursor::new();
+        cursor
+            .matches(&query.inner, node, text)
+            .map(|r#match| {
+                let captures = r#match
+                    .captures
+                    .iter()
+                    .map(|capture| QueryCapture {
+                        node: capture.node.into(),
+                        index: capture.index,
+                    })
+                    .collect();
+                QueryMatch {
+                    pattern_index: r#match.pattern_index,
+                    captures,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::{Query, QueryCursor};
+    use crate::core::{
+        test::{language, parse},
+        Error,
+    };
+
+    /// Return the text of the nodes captured by the query in the source.
+    fn captures(query: &str, source: &str) -> Vec<String> {
+        let query = Query::new(&language(), query).unwrap();
+        let (content, tree) = parse(source);
+        QueryCursor::new()
+            .matches(&query, &tree.root_node(), &content)
+            .iter()
+            .flat_map(|r#match| &r#match.captures)
+            .map(|capture| {
+                let node = &capture.node;
+                content.byte_slice(node.start_byte() as usize .. node.end_byte() as usize).to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn capture_names() {
+        let query = Query::new(&language(), "(call_expression function: (identifier) @function) @call").unwrap();
+        assert_eq!(query.capture_names(), ["function", "call"]);
+        assert_eq!(query.capture_index_for_name("call"), Some(1));
+        assert_eq!(query.pattern_count(), 1);
+        assert_eq!(captures("(identifier) @name", "a(b);"), ["a", "b"]);
+    }
+
+    #[test]
+    fn eq_predicates() {
+        let source = "a; b; a.c;";
+        assert_eq!(captures("((identifier) @name (#eq? @name \"a\"))", source), ["a", "a"]);
+        assert_eq!(captures("((identifier) @name (#not-eq? @name \"a\"))", source), ["b"]);
+    }
+
+    #[test]
+    fn match_predicates() {
+        let source = "Foo; bar; BAZ;";
+        assert_eq!(captures("((identifier) @name (#match? @name \"^[A-Z]\"))", source), ["Foo", "BAZ"]);
+        assert_eq!(captures("((identifier) @name (#not-match? @name \"^[A-Z]+$\"))", source), ["Foo", "bar"]);
+    }
+
+    #[test]
+    fn rejects_unsupported_predicates() {
+        let error = Query::new(&language(), "(identifier)\n((identifier) @name (#foo? @name \"a\"))").unwrap_err();
+        let message = "unsupported predicate `#foo?`".to_string();
+        assert_eq!(error.downcast::<Error>().unwrap(), Error::InvalidQuery { offset: 13, message });
+    }
+
+    #[test]
+    fn rejects_invalid_queries() {
+        let error = Query::new(&language(), "(identifier) @name (nonexistent)").unwrap_err();
+        assert!(matches!(error.downcast::<Error>().unwrap(), Error::InvalidQuery { offset: 20, .. }));
+    }
+}
@@ -0,0 +1,764 @@
+use lsp_text::RopeExt;
+use std::ops::Range;
+
+pub type ScopeId = usize;
+pub type BindingId = usize;
+pub type ReferenceId = usize;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScopeKind {
+    Module,
+    Function,
+    Block,
+    Class,
+    Catch,
+    For,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BindingKind {
+    Var,
+    Let,
+    Const,
+    Function,
+    Class,
+    Parameter,
+    CatchParameter,
+    Import,
+}
+
+impl BindingKind {
+    /// The keyword used to introduce a binding of this kind, if any.
+    pub fn keyword(&self) -> Option<&'static str> {
+        match self {
+            BindingKind::Var => Some("var"),
+            BindingKind::Let => Some("let"),
+            BindingKind::Const => Some("const"),
+            BindingKind::Function => Some("function"),
+            BindingKind::Class => Some("class"),
+            BindingKind::Import => Some("import"),
+            BindingKind::Parameter | BindingKind::CatchParameter => None,
+        }
+    }
+}
+
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    /// The byte range of the node introducing the scope.
+    pub span: Range<u32>,
+    pub bindings: Vec<BindingId>,
+}
+
+pub struct Binding {
+    pub name: String,
+    pub kind: BindingKind,
+    pub scope: ScopeId,
+    /// The byte range of the identifier introducing the binding.
+    pub span: Range<u32>,
+    pub range: lsp::Range,
+    /// The byte range of the node declaring the binding, e.g., the `function_declaration` or the
+    /// `variable_declarator`.
+    pub declaration: Range<u32>,
+    pub references: Vec<ReferenceId>,
+}
+
+pub struct Reference {
+    pub name: String,
+    pub scope: ScopeId,
+    pub span: Range<u32>,
+    pub range: lsp::Range,
+    /// The binding the reference resolves to, or [`None`] for globals and undeclared names.
+    pub binding: Option<BindingId>,
+    pub write: bool,
+}
+
+/// An occurrence of a name in the document.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Occurrence {
+    Binding(BindingId),
+    Reference(ReferenceId),
+}
+
+/// The scopes of a document together with the bindings they declare and the references resolved
+/// against them.
+#[derive(Default)]
+pub struct Scopes {
+    pub scopes: Vec<Scope>,
+    pub bindings: Vec<Binding>,
+    pub references: Vec<Reference>,
+}
+
+impl Scopes {
+    pub fn analyze(content: &ropey::Rope, tree: &tree_sitter::Tree) -> Self {
+        let mut builder = Builder {
+            content,
+            scopes: Scopes::default(),
+        };
+        let root = tree.root_node();
+        let module = builder.push_scope(ScopeKind::Module, None, &root);
+        builder.visit_children(&root, module);
+        builder.resolve();
+        builder.scopes
+    }
+
+    /// Find the binding for `name` visible from `scope`.
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<BindingId> {
+        self.ancestors(scope).find_map(|scope| self.lookup_local(scope, name))
+    }
+
+    /// Find the binding for `name` declared directly in `scope`.
+    pub fn lookup_local(&self, scope: ScopeId, name: &str) -> Option<BindingId> {
+        self.scopes[scope]
+            .bindings
+            .iter()
+            .copied()
+            .find(|&binding| self.bindings[binding].name == name)
+    }
+
+    /// Iterate over `scope` and its enclosing scopes, from the innermost outwards.
+    pub fn ancestors(&self, scope: ScopeId) -> impl Iterator<Item = ScopeId> + '_ {
+        std::iter::successors(Some(scope), move |&scope| self.scopes[scope].parent)
+    }
+
+    /// Return the innermost scope containing the byte offset.
+    pub fn scope_at(&self, byte: u32) -> ScopeId {
+        // NOTE: scopes are created in document order so the last one containing the offset is the
+        // innermost one.
+        self.scopes
+            .iter()
+            .rposition(|scope| scope.span.start <= byte && byte <= scope.span.end)
+            .unwrap_or_default()
+    }
+
+    /// Return the bindings visible from `scope`, omitting those shadowed by closer bindings.
+    pub fn visible_bindings(&self, scope: ScopeId) -> Vec<BindingId> {
+        let mut result: Vec<BindingId> = vec![];
+        for scope in self.ancestors(scope) {
+            for &binding in &self.scopes[scope].bindings {
+                let name = &self.bindings[binding].name;
+                if !result.iter().any(|&other| &self.bindings[other].name == name) {
+                    result.push(binding);
+                }
+            }
+        }
+        result
+    }
+
+    /// Return the binding or reference whose identifier contains the byte offset.
+    pub fn occurrence_at(&self, byte: u32) -> Option<Occurrence> {
+        let contains = |span: &Range<u32>| span.start <= byte && byte <= span.end;
+        if let Some(binding) = self.bindings.iter().position(|binding| contains(&binding.span)) {
+            return Some(Occurrence::Binding(binding));
+        }
+        self.references
+            .iter()
+            .position(|reference| contains(&reference.span))
+            .map(Occurrence::Reference)
+    }
+
+    /// Return the binding an occurrence refers to.
+    pub fn binding_of(&self, occurrence: Occurrence) -> Option<BindingId> {
+        match occurrence {
+            Occurrence::Binding(binding) => Some(binding),
+            Occurrence::Reference(reference) => self.references[reference].binding,
+        }
+    }
+
+    /// Return every occurrence of the same name as the given occurrence: the binding followed by
+    /// its references. For unresolved references, this is every unresolved reference to the name.
+    pub fn related(&self, occurrence: Occurrence) -> Vec<Occurrence> {
+        if let Some(binding) = self.binding_of(occurrence) {
+            let references = self.bindings[binding].references.iter().copied().map(Occurrence::Reference);
+            std::iter::once(Occurrence::Binding(binding)).chain(references).collect()
+        } else {
+            let name = &self.name_of(occurrence);
+            (0 .. self.references.len())
+                .filter(|&reference| {
+                    let reference = &self.references[reference];
+                    reference.binding.is_none() && &reference.name == name
+                })
+                .map(Occurrence::Reference)
+                .collect()
+        }
+    }
+
+    pub fn name_of(&self, occurrence: Occurrence) -> &str {
+        match occurrence {
+            Occurrence::Binding(binding) => &self.bindings[binding].name,
+            Occurrence::Reference(reference) => &self.references[reference].name,
+        }
+    }
+
+    pub fn range_of(&self, occurrence: Occurrence) -> lsp::Range {
+        match occurrence {
+            Occurrence::Binding(binding) => self.bindings[binding].range,
+            Occurrence::Reference(reference) => self.references[reference].range,
+        }
+    }
+
+    pub fn span_of(&self, occurrence: Occurrence) -> Range<u32> {
+        match occurrence {
+            Occurrence::Binding(binding) => self.bindings[binding].span.clone(),
+            Occurrence::Reference(reference) => self.references[reference].span.clone(),
+        }
+    }
+
+    /// Whether the occurrence assigns to the binding. Declarations count as writes.
+    pub fn is_write(&self, occurrence: Occurrence) -> bool {
+        match occurrence {
+            Occurrence::Binding(_) => true,
+            Occurrence::Reference(reference) => self.references[reference].write,
+        }
+    }
+
+    /// Return the binding whose identifier contains the byte offset, or which the reference at the
+    /// byte offset resolves to.
+    pub fn binding_at(&self, byte: u32) -> Option<BindingId> {
+        self.occurrence_at(byte).and_then(|occurrence| self.binding_of(occurrence))
+    }
+}
+
+struct Builder<'a> {
+    content: &'a ropey::Rope,
+    scopes: Scopes,
+}
+
+impl<'a> Builder<'a> {
+    fn push_scope(&mut self, kind: ScopeKind, parent: Option<ScopeId>, node: &tree_sitter::Node) -> ScopeId {
+        let id = self.scopes.scopes.len();
+        self.scopes.scopes.push(Scope {
+            kind,
+            parent,
+            span: node.start_byte() .. node.end_byte(),
+            bindings: vec![],
+        });
+        id
+    }
+
+    /// The scope receiving hoisted `var` declarations made in `scope`.
+    fn hoist_target(&self, scope: ScopeId) -> ScopeId {
+        self.scopes
+            .ancestors(scope)
+            .find(|&scope| matches!(self.scopes.scopes[scope].kind, ScopeKind::Function | ScopeKind::Module))
+            .unwrap_or(scope)
+    }
+
+    fn declare(
+        &mut self,
+        scope: ScopeId,
+        kind: BindingKind,
+        identifier: &tree_sitter::Node,
+        declaration: &tree_sitter::Node,
+    ) {
+        let name = self.content.utf8_text_for_tree_sitter_node(identifier).to_string();
+
+        // NOTE: redeclaring a `var` or a function in the same scope refers to the same binding, so
+        // the redeclaration is recorded as a write to it instead.
+        if matches!(kind, BindingKind::Var | BindingKind::Function) {
+            if let Some(existing) = self.scopes.lookup_local(scope, &name) {
+                let existing_kind = self.scopes.bindings[existing].kind;
+                if matches!(existing_kind, BindingKind::Var | BindingKind::Function | BindingKind::Parameter) {
+                    let reference = self.push_reference(scope, identifier, true);
+                    self.scopes.references[reference].binding = Some(existing);
+                    self.scopes.bindings[existing].references.push(reference);
+                    return;
+                }
+            }
+        }
+
+        let id = self.scopes.bindings.len();
+        self.scopes.bindings.push(Binding {
+            name,
+            kind,
+            scope,
+            span: identifier.start_byte() .. identifier.end_byte(),
+            range: self.content.tree_sitter_range_to_lsp_range(identifier.range()),
+            declaration: declaration.start_byte() .. declaration.end_byte(),
+            references: vec![],
+        });
+        self.scopes.scopes[scope].bindings.push(id);
+    }
+
+    fn push_reference(&mut self, scope: ScopeId, identifier: &tree_sitter::Node, write: bool) -> ReferenceId {
+        let id = self.scopes.references.len();
+        self.scopes.references.push(Reference {
+            name: self.content.utf8_text_for_tree_sitter_node(identifier).to_string(),
+            scope,
+            span: identifier.start_byte() .. identifier.end_byte(),
+            range: self.content.tree_sitter_range_to_lsp_range(identifier.range()),
+            binding: None,
+            write,
+        });
+        id
+    }
+
+    // NOTE: references are resolved only once every binding has been declared since function
+    // declarations and `var` bindings are visible before the point they are declared.
+    fn resolve(&mut self) {
+        for id in 0 .. self.scopes.references.len() {
+            let reference = &self.scopes.references[id];
+            if reference.binding.is_some() {
+                continue;
+            }
+            if let Some(binding) = self.scopes.lookup(reference.scope, &reference.name) {
+                self.scopes.references[id].binding = Some(binding);
+                self.scopes.bindings[binding].references.push(id);
+            }
+        }
+    }
+
+    fn visit_children(&mut self, node: &tree_sitter::Node, scope: ScopeId) {
+        for i in 0 .. node.named_child_count() {
+            if let Some(child) = node.named_child(i) {
+                self.visit(&child, scope);
+            }
+        }
+    }
+
+    fn visit(&mut self, node: &tree_sitter::Node, scope: ScopeId) {
+        let kind = node.kind();
+        match &*kind {
+            "identifier" | "shorthand_property_identifier" => {
+                self.push_reference(scope, node, false);
+            },
+            "function_declaration" | "generator_function_declaration" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.declare(scope, BindingKind::Function, &name, node);
+                }
+                self.visit_function(node, scope);
+            },
+            "function" | "generator_function" | "arrow_function" | "method_definition" => {
+                self.visit_function(node, scope);
+            },
+            "class_declaration" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.declare(scope, BindingKind::Class, &name, node);
+                }
+                self.visit_class(node, scope);
+            },
+            "class" => self.visit_class(node, scope),
+            "lexical_declaration" | "variable_declaration" => self.visit_declaration(node, scope),
+            "statement_block" | "switch_body" => {
+                let block = self.push_scope(ScopeKind::Block, Some(scope), node);
+                self.visit_children(node, block);
+            },
+            "for_statement" => {
+                let block = self.push_scope(ScopeKind::For, Some(scope), node);
+                self.visit_children(node, block);
+            },
+            "for_in_statement" => self.visit_for_in(node, scope),
+            "catch_clause" => {
+                let block = self.push_scope(ScopeKind::Catch, Some(scope), node);
+                if let Some(parameter) = node.child_by_field_name("parameter") {
+                    let declare = Some((block, BindingKind::CatchParameter, node));
+                    self.visit_pattern(&parameter, block, declare);
+                }
+                if let Some(body) = node.child_by_field_name("body") {
+                    self.visit_children(&body, block);
+                }
+            },
+            "import_statement" => self.visit_import(node, scope),
+            "export_statement" => self.visit_export(node, scope),
+            "assignment_expression" | "augmented_assignment_expression" => {
+                if let Some(left) = node.child_by_field_name("left") {
+                    self.visit_pattern(&left, scope, None);
+                }
+                if let Some(right) = node.child_by_field_name("right") {
+                    self.visit(&right, scope);
+                }
+            },
+            "update_expression" => match node.child_by_field_name("argument") {
+                Some(argument) if argument.kind() == "identifier" => {
+                    self.push_reference(scope, &argument, true);
+                },
+                _ => self.visit_children(node, scope),
+            },
+            _ => self.visit_children(node, scope),
+        }
+    }
+
+    fn visit_function(&mut self, node: &tree_sitter::Node, scope: ScopeId) {
+        // NOTE: computed method names are evaluated in the enclosing scope.
+        if node.kind() == "method_definition" {
+            if let Some(name) = node.child_by_field_name("name") {
+                if name.kind() == "computed_property_name" {
+                    self.visit(&name, scope);
+                }
+            }
+        }
+
+        let function = self.push_scope(ScopeKind::Function, Some(scope), node);
+
+        // NOTE: the name of a function expression is only visible inside of the function itself.
+        if matches!(&*node.kind(), "function" | "generator_function") {
+            if let Some(name) = node.child_by_field_name("name") {
+                self.declare(function, BindingKind::Function, &name, node);
+            }
+        }
+
+        if let Some(parameters) = node.child_by_field_name("parameters") {
+            for i in 0 .. parameters.named_child_count() {
+                if let Some(parameter) = parameters.named_child(i) {
+                    let declare = Some((function, BindingKind::Parameter, node));
+                    self.visit_pattern(&parameter, function, declare);
+                }
+            }
+        }
+        if let Some(parameter) = node.child_by_field_name("parameter") {
+            let declare = Some((function, BindingKind::Parameter, node));
+            self.visit_pattern(&parameter, function, declare);
+        }
+
+        if let Some(body) = node.child_by_field_name("body") {
+            // NOTE: the body of a function shares the scope of its parameters.
+            if body.kind() == "statement_block" {
+                self.visit_children(&body, function);
+            } else {
+                self.visit(&body, function);
+            }
+        }
+    }
+
+    fn visit_class(&mut self, node: &tree_sitter::Node, scope: ScopeId) {
+        for i in 0 .. node.named_child_count() {
+            if let Some(child) = node.named_child(i) {
+                if child.kind() == "class_heritage" {
+                    self.visit_children(&child, scope);
+                }
+            }
+        }
+
+        let class = self.push_scope(ScopeKind::Class, Some(scope), node);
+
+        // NOTE: the name of a class expression is only visible inside of the class itself.
+        if node.kind() == "class" {
+            if let Some(name) = node.child_by_field_name("name") {
+                self.declare(class, BindingKind::Class, &name, node);
+            }
+        }
+
+        if let Some(body) = node.child_by_field_name("body") {
+            self.visit_children(&body, class);
+        }
+    }
+
+    fn visit_declaration(&mut self, node: &tree_sitter::Node, scope: ScopeId) {
+        let kind = match node.child(0).map(|keyword| keyword.kind().to_string()).as_deref() {
+            Some("const") => BindingKind::Const,
+            Some("let") => BindingKind::Let,
+            _ => BindingKind::Var,
+        };
+        let target = if kind == BindingKind::Var {
+            self.hoist_target(scope)
+        } else {
+            scope
+        };
+
+        for i in 0 .. node.named_child_count() {
+            let declarator = match node.named_child(i) {
+                Some(declarator) if declarator.kind() == "variable_declarator" => declarator,
+                _ => continue,
+            };
+            if let Some(name) = declarator.child_by_field_name("name") {
+                self.visit_pattern(&name, scope, Some((target, kind, &declarator)));
+            }
+            if let Some(value) = declarator.child_by_field_name("value") {
+                self.visit(&value, scope);
+            }
+        }
+    }
+
+    fn visit_for_in(&mut self, node: &tree_sitter::Node, scope: ScopeId) {
+        let block = self.push_scope(ScopeKind::For, Some(scope), node);
+
+        let keyword = (0 .. node.child_count())
+            .filter_map(|i| node.child(i))
+            .filter(|child| !child.is_named())
+            .find_map(|child| match &*child.kind() {
+                "const" => Some(BindingKind::Const),
+                "let" => Some(BindingKind::Let),
+                "var" => Some(BindingKind::Var),
+                _ => None,
+            });
+
+        if let Some(left) = node.child_by_field_name("left") {
+            let declare = keyword.map(|kind| {
+                let target = if kind == BindingKind::Var {
+                    self.hoist_target(block)
+                } else {
+                    block
+                };
+                (target, kind, node)
+            });
+            self.visit_pattern(&left, block, declare);
+        }
+        if let Some(right) = node.child_by_field_name("right") {
+            self.visit(&right, block);
+        }
+        if let Some(body) = node.child_by_field_name("body") {
+            self.visit(&body, block);
+        }
+    }
+
+    /// Visit a binding or assignment pattern. When `declare` is given, the identifiers in the
+    /// pattern are declared in the target scope with the given kind; otherwise they are recorded as
+    /// writes. Default values are evaluated in `scope`.
+    fn visit_pattern(
+        &mut self,
+        pattern: &tree_sitter::Node,
+        scope: ScopeId,
+        declare: Option<(ScopeId, BindingKind, &tree_sitter::Node)>,
+    ) {
+        let kind = pattern.kind();
+        match &*kind {
+            "identifier" | "shorthand_property_identifier_pattern" => {
+                if let Some((target, kind, declaration)) = declare {
+                    self.declare(target, kind, pattern, declaration);
+                } else {
+                    self.push_reference(scope, pattern, true);
+                }
+            },
+            "object_pattern" | "array_pattern" | "rest_pattern" => {
+                for i in 0 .. pattern.named_child_count() {
+                    if let Some(child) = pattern.named_child(i) {
+                        self.visit_pattern(&child, scope, declare);
+                    }
+                }
+            },
+            "pair_pattern" => {
+                if let Some(key) = pattern.child_by_field_name("key") {
+                    if key.kind() == "computed_property_name" {
+                        self.visit(&key, scope);
+                    }
+                }
+                if let Some(value) = pattern.child_by_field_name("value") {
+                    self.visit_pattern(&value, scope, declare);
+                }
+            },
+            "assignment_pattern" | "object_assignment_pattern" => {
+                if let Some(left) = pattern.child_by_field_name("left") {
+                    self.visit_pattern(&left, scope, declare);
+                }
+                if let Some(right) = pattern.child_by_field_name("right") {
+                    self.visit(&right, scope);
+                }
+            },
+            // NOTE: in assignments the left-hand side may also be an arbitrary member expression.
+            _ => self.visit(pattern, scope),
+        }
+    }
+
+    fn visit_import(&mut self, node: &tree_sitter::Node, scope: ScopeId) {
+        let clause = (0 .. node.named_child_count())
+            .filter_map(|i| node.named_child(i))
+            .find(|child| child.kind() == "import_clause");
+        let clause = match clause {
+            Some(clause) => clause,
+            None => return,
+        };
+
+        for i in 0 .. clause.named_child_count() {
+            let child = match clause.named_child(i) {
+                Some(child) => child,
+                None => continue,
+            };
+            match &*child.kind() {
+                "identifier" => self.declare(scope, BindingKind::Import, &child, node),
+                "namespace_import" => {
+                    let identifier = (0 .. child.named_child_count())
+                        .filter_map(|i| child.named_child(i))
+                        .find(|child| child.kind() == "identifier");
+                    if let Some(identifier) = identifier {
+                        self.declare(scope, BindingKind::Import, &identifier, node);
+                    }
+                },
+                "named_imports" => {
+                    for i in 0 .. child.named_child_count() {
+                        let specifier = match child.named_child(i) {
+                            Some(specifier) if specifier.kind() == "import_specifier" => specifier,
+                            _ => continue,
+                        };
+                        let identifier = specifier
+                            .child_by_field_name("alias")
+                            .or_else(|| specifier.child_by_field_name("name"));
+                        if let Some(identifier) = identifier {
+                            self.declare(scope, BindingKind::Import, &identifier, &specifier);
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn visit_export(&mut self, node: &tree_sitter::Node, scope: ScopeId) {
+        if let Some(declaration) = node.child_by_field_name("declaration") {
+            self.visit(&declaration, scope);
+        }
+        if let Some(value) = node.child_by_field_name("value") {
+            self.visit(&value, scope);
+        }
+
+        // NOTE: the names in `export { x } from "y"` refer to the other module, not to local bindings.
+        if node.child_by_field_name("source").is_some() {
+            return;
+        }
+
+        for i in 0 .. node.named_child_count() {
+            let clause = match node.named_child(i) {
+                Some(clause) if clause.kind() == "export_clause" => clause,
+                _ => continue,
+            };
+            for i in 0 .. clause.named_child_count() {
+                let specifier = match clause.named_child(i) {
+                    Some(specifier) if specifier.kind() == "export_specifier" => specifier,
+                    _ => continue,
+                };
+                if let Some(name) = specifier.child_by_field_name("name") {
+                    if name.kind() == "identifier" {
+                        self.push_reference(scope, &name, false);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::{BindingKind, Occurrence, ScopeKind, Scopes};
+    use crate::core::test::parse;
+
+    fn analyze(source: &str) -> Scopes {
+        let (content, tree) = parse(source);
+        Scopes::analyze(&content, &tree)
+    }
+
+    /// Return the offset of the `nth` occurrence of the name, as a whole word.
+    fn offset(source: &str, name: &str, nth: usize) -> u32 {
+        let is_word = |c: char| c == '_' || c.is_alphanumeric();
+        let (offset, _) = source
+            .match_indices(name)
+            .filter(|&(offset, _)| {
+                let before = source[.. offset].chars().next_back();
+                let after = source[offset + name.len() ..].chars().next();
+                !before.map_or(false, is_word) && !after.map_or(false, is_word)
+            })
+            .nth(nth)
+            .unwrap();
+        offset as u32
+    }
+
+    /// Return the kind of the binding the `nth` occurrence of the name refers to, and the kind of
+    /// the scope declaring it.
+    fn resolve(scopes: &Scopes, source: &str, name: &str, nth: usize) -> Option<(BindingKind, ScopeKind)> {
+        let binding = &scopes.bindings[scopes.binding_at(offset(source, name, nth))?];
+        Some((binding.kind, scopes.scopes[binding.scope].kind))
+    }
+
+    #[test]
+    fn var_is_hoisted_to_the_function() {
+        let source = "function f() { a; { var a = 1; } a; } a;";
+        let scopes = analyze(source);
+        let expected = Some((BindingKind::Var, ScopeKind::Function));
+        assert_eq!(resolve(&scopes, source, "a", 0), expected);
+        assert_eq!(resolve(&scopes, source, "a", 1), expected);
+        assert_eq!(resolve(&scopes, source, "a", 2), expected);
+        assert_eq!(resolve(&scopes, source, "a", 3), None);
+    }
+
+    #[test]
+    fn var_redeclaration_writes_to_the_same_binding() {
+        let source = "var a = 1; var a = 2;";
+        let scopes = analyze(source);
+        assert_eq!(scopes.bindings.len(), 1);
+        let occurrence = scopes.occurrence_at(offset(source, "a", 1)).unwrap();
+        assert_eq!(occurrence, Occurrence::Reference(0));
+        assert!(scopes.is_write(occurrence));
+    }
+
+    #[test]
+    fn let_and_const_are_block_scoped() {
+        // NOTE: within the block, `a` refers to the `const` even before its declaration (the
+        // temporal dead zone), rather than to the outer `let`.
+        let source = "let a = 1; { a; const a = 2; a; } a;";
+        let scopes = analyze(source);
+        assert_eq!(resolve(&scopes, source, "a", 1), Some((BindingKind::Const, ScopeKind::Block)));
+        assert_eq!(resolve(&scopes, source, "a", 2), Some((BindingKind::Const, ScopeKind::Block)));
+        assert_eq!(resolve(&scopes, source, "a", 3), Some((BindingKind::Const, ScopeKind::Block)));
+        assert_eq!(resolve(&scopes, source, "a", 4), Some((BindingKind::Let, ScopeKind::Module)));
+    }
+
+    #[test]
+    fn catch_parameters() {
+        let source = "try {} catch ({ e }) { e; } e;";
+        let scopes = analyze(source);
+        assert_eq!(resolve(&scopes, source, "e", 1), Some((BindingKind::CatchParameter, ScopeKind::Catch)));
+        assert_eq!(resolve(&scopes, source, "e", 2), None);
+    }
+
+    #[test]
+    fn for_bindings() {
+        let source = "for (let i = 0; i < 1; i++) { i; } for (const k in o) { k; } for (var v of o) {} i; k; v;";
+        let scopes = analyze(source);
+        assert_eq!(resolve(&scopes, source, "i", 1), Some((BindingKind::Let, ScopeKind::For)));
+        assert_eq!(resolve(&scopes, source, "i", 3), Some((BindingKind::Let, ScopeKind::For)));
+        assert_eq!(resolve(&scopes, source, "i", 4), None);
+        assert_eq!(resolve(&scopes, source, "k", 1), Some((BindingKind::Const, ScopeKind::For)));
+        assert_eq!(resolve(&scopes, source, "k", 2), None);
+        assert_eq!(resolve(&scopes, source, "v", 1), Some((BindingKind::Var, ScopeKind::Module)));
+
+        let update = scopes.occurrence_at(offset(source, "i", 2)).unwrap();
+        assert!(scopes.is_write(update));
+    }
+
+    #[test]
+    fn class_names() {
+        let source = "class A { m() { A; B; } } let C = class B { m() { B; } }; A; B;";
+        let scopes = analyze(source);
+        assert_eq!(resolve(&scopes, source, "A", 1), Some((BindingKind::Class, ScopeKind::Module)));
+        assert_eq!(resolve(&scopes, source, "B", 0), None);
+        assert_eq!(resolve(&scopes, source, "B", 2), Some((BindingKind::Class, ScopeKind::Class)));
+        assert_eq!(resolve(&scopes, source, "A", 2), Some((BindingKind::Class, ScopeKind::Module)));
+        assert_eq!(resolve(&scopes, source, "B", 3), None);
+    }
+
+    #[test]
+    fn shadowing() {
+        let source = "let a; function f(a) { a; { let a; a; } } a;";
+        let scopes = analyze(source);
+        let parameter = scopes.binding_at(offset(source, "a", 1));
+        assert_eq!(resolve(&scopes, source, "a", 1), Some((BindingKind::Parameter, ScopeKind::Function)));
+        assert_eq!(scopes.binding_at(offset(source, "a", 2)), parameter);
+        assert_eq!(resolve(&scopes, source, "a", 4), Some((BindingKind::Let, ScopeKind::Block)));
+        assert_eq!(resolve(&scopes, source, "a", 5), Some((BindingKind::Let, ScopeKind::Module)));
+        assert_ne!(scopes.binding_at(offset(source, "a", 4)), scopes.binding_at(offset(source, "a", 5)));
+    }
+
+    #[test]
+    fn import_and_export_specifiers() {
+        let source = concat!(
+            "import d, { a, b as c } from 'm'; import * as n from 'n';",
+            "export { a, c as e }; export { x } from 'y';",
+        );
+        let scopes = analyze(source);
+        let names: Vec<_> = scopes.bindings.iter().map(|binding| binding.name.as_str()).collect();
+        assert_eq!(names, ["d", "a", "c", "n"]);
+        assert!(scopes.bindings.iter().all(|binding| binding.kind == BindingKind::Import));
+
+        assert_eq!(scopes.binding_at(offset(source, "a", 1)), Some(1));
+        assert_eq!(scopes.binding_at(offset(source, "c", 1)), Some(2));
+        // NOTE: the exported names and the re-exported `x` are not references to local bindings.
+        assert_eq!(scopes.occurrence_at(offset(source, "e", 0)), None);
+        assert_eq!(scopes.occurrence_at(offset(source, "x", 0)), None);
+    }
+
+    #[test]
+    fn shorthand_properties() {
+        let source = "let a = 1; let o = { a }; let { b } = o; b;";
+        let scopes = analyze(source);
+        assert_eq!(scopes.binding_at(offset(source, "a", 1)), scopes.binding_at(offset(source, "a", 0)));
+        assert_eq!(resolve(&scopes, source, "b", 1), Some((BindingKind::Let, ScopeKind::Module)));
+    }
+}
@@ -1,5 +1,4 @@
-use anyhow::anyhow;
-use async_lock::{Mutex, RwLock};
+use async_lock::RwLock;
 use dashmap::{
     mapref::one::{Ref, RefMut},
     DashMap,
@@ -9,38 +8,49 @@ use std::sync::Arc;
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SessionResourceKind {
     Document,
-    Parser,
-    Tree,
 }
 
 pub struct Session {
     pub server_capabilities: RwLock<lsp::ServerCapabilities>,
     pub client_capabilities: RwLock<Option<lsp::ClientCapabilities>>,
+    pub settings: RwLock<crate::core::settings::Settings>,
     client: Option<tower_lsp::Client>,
     pub language: tree_sitter::Language,
-    pub document_states: DashMap<lsp::Url, crate::core::DocumentState>,
-    document_texts: DashMap<lsp::Url, crate::core::Text>,
-    document_parsers: DashMap<lsp::Url, Mutex<tree_sitter::Parser>>,
-    document_trees: DashMap<lsp::Url, Mutex<tree_sitter::Tree>>,
+    documents: DashMap<lsp::Url, crate::core::DocumentEntry>,
+    pub semantic_tokens: DashMap<lsp::Url, crate::provider::semantic_tokens::Cache>,
+    pub reference_index: DashMap<lsp::Url, crate::provider::code_lens::Index>,
+    pub workspace_symbols: crate::provider::workspace_symbol::Index,
+    /// Compiled queries, keyed by the language ID and the source of the query.
+    queries: DashMap<(&'static str, &'static str), Arc<crate::core::query::Query>>,
+    pub syntax_tree_sink: Option<Box<dyn crate::core::syntax::SyntaxTreeSink>>,
 }
 
 impl Session {
-    pub fn new(client: Option<tower_lsp::Client>, language: tree_sitter::Language) -> Arc<Self> {
+    pub fn new(
+        client: Option<tower_lsp::Client>,
+        language: tree_sitter::Language,
+        syntax_tree_sink: Option<Box<dyn crate::core::syntax::SyntaxTreeSink>>,
+    ) -> Arc<Self> {
         let server_capabilities = RwLock::new(crate::server::capabilities());
         let client_capabilities = Default::default();
-        let document_states = Default::default();
-        let document_texts = Default::default();
-        let document_parsers = Default::default();
-        let document_trees = Default::default();
+        let settings = Default::default();
+        let documents = Default::default();
+        let semantic_tokens = Default::default();
+        let reference_index = Default::default();
+        let workspace_symbols = Default::default();
+        let queries = Default::default();
         Arc::new(Session {
             server_capabilities,
             client_capabilities,
+            settings,
             client,
             language,
-            document_states,
-            document_texts,
-            document_parsers,
-            document_trees,
+            documents,
+            semantic_tokens,
+            reference_index,
+            workspace_symbols,
+            queries,
+            syntax_tree_sink,
         })
     }
 
@@ -50,26 +60,58 @@ impl Session {
             .ok_or_else(|| crate::core::Error::ClientNotInitialized.into())
     }
 
-    pub fn insert_document(&self, uri: lsp::Url, document: crate::core::Document) -> anyhow::Result<()> {
-        let result = self.document_texts.insert(uri.clone(), document.text());
-        debug_assert!(result.is_none());
-        let result = self.document_parsers.insert(uri.clone(), Mutex::new(document.parser));
-        debug_assert!(result.is_none());
-        let result = self.document_trees.insert(uri, Mutex::new(document.tree));
-        debug_assert!(result.is_none());
+    pub fn insert_document(&self, uri: lsp::Url, document: crate::core::DocumentEntry) -> anyhow::Result<()> {
+        if self.documents.insert(uri.clone(), document).is_some() {
+            log::warn!("document was already open :: uri: {:#?}", uri);
+        }
         Ok(())
     }
 
     pub fn remove_document(&self, uri: &lsp::Url) -> anyhow::Result<()> {
-        let result = self.document_texts.remove(uri);
-        debug_assert!(result.is_some());
-        let result = self.document_parsers.remove(uri);
-        debug_assert!(result.is_some());
-        let result = self.document_trees.remove(uri);
-        debug_assert!(result.is_some());
+        self.documents.remove(uri).ok_or_else(|| {
+            let kind = SessionResourceKind::Document;
+            let uri = uri.clone();
+            crate::core::Error::SessionResourceNotFound { kind, uri }
+        })?;
+        self.semantic_tokens.remove(uri);
+        self.reference_index.remove(uri);
+        self.workspace_symbols.remove(uri);
         Ok(())
     }
 
+    /// Return the compiled query for the source, compiling it the first time it is requested.
+    pub fn query(&self, source: &'static str) -> anyhow::Result<Arc<crate::core::query::Query>> {
+        let key = (crate::core::language::ID, source);
+        if let Some(query) = self.queries.get(&key) {
+            return Ok(query.clone());
+        }
+        let query = Arc::new(crate::core::query::Query::new(&self.language, source)?);
+        self.queries.insert(key, query.clone());
+        Ok(query)
+    }
+
+    /// Whether the syntax trees of documents are sent anywhere, either to the client or to the sink.
+    pub async fn wants_syntax_trees(&self) -> bool {
+        self.syntax_tree_sink.is_some() || self.settings.read().await.syntax_tree
+    }
+
+    /// Return the URIs of the open documents.
+    pub fn document_uris(&self) -> Vec<lsp::Url> {
+        self.documents.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    /// Return an error if the document is no longer at the version, e.g., because it was changed
+    /// or closed while a request was computed from that version.
+    pub fn ensure_version(&self, uri: &lsp::Url, version: i32) -> anyhow::Result<()> {
+        match self.documents.get(uri) {
+            Some(document) if document.version == version => Ok(()),
+            _ => {
+                let uri = uri.clone();
+                Err(crate::core::Error::ContentModified { uri }.into())
+            },
+        }
+    }
+
     pub async fn semantic_tokens_legend(&self) -> Option<lsp::SemanticTokensLegend> {
         let capabilities = self.server_capabilities.read().await;
         if let Some(capabilities) = &capabilities.semantic_tokens_provider {
@@ -84,59 +126,22 @@ impl Session {
         }
     }
 
-    pub async fn get_text(&self, uri: &lsp::Url) -> anyhow::Result<Ref<'_, lsp::Url, crate::core::Text>> {
-        self.document_texts.get(uri).ok_or_else(|| {
-            let kind = SessionResourceKind::Document;
-            let uri = uri.clone();
-            crate::core::Error::SessionResourceNotFound { kind, uri }.into()
-        })
-    }
-
-    pub async fn get_mut_text(&self, uri: &lsp::Url) -> anyhow::Result<RefMut<'_, lsp::Url, crate::core::Text>> {
-        self.document_texts.get_mut(uri).ok_or_else(|| {
+    pub async fn get_document(&self, uri: &lsp::Url) -> anyhow::Result<Ref<'_, lsp::Url, crate::core::DocumentEntry>> {
+        self.documents.get(uri).ok_or_else(|| {
             let kind = SessionResourceKind::Document;
             let uri = uri.clone();
             crate::core::Error::SessionResourceNotFound { kind, uri }.into()
         })
     }
 
-    pub async fn get_mut_parser(
+    pub async fn get_mut_document(
         &self,
         uri: &lsp::Url,
-    ) -> anyhow::Result<RefMut<'_, lsp::Url, Mutex<tree_sitter::Parser>>> {
-        self.document_parsers.get_mut(uri).ok_or_else(|| {
-            let kind = SessionResourceKind::Parser;
-            let uri = uri.clone();
-            crate::core::Error::SessionResourceNotFound { kind, uri }.into()
-        })
-    }
-
-    pub async fn get_tree(&self, uri: &lsp::Url) -> anyhow::Result<Ref<'_, lsp::Url, Mutex<tree_sitter::Tree>>> {
-        self.document_trees.get(uri).ok_or_else(|| {
-            let kind = SessionResourceKind::Tree;
-            let uri = uri.clone();
-            crate::core::Error::SessionResourceNotFound { kind, uri }.into()
-        })
-    }
-
-    pub async fn get_mut_tree(&self, uri: &lsp::Url) -> anyhow::Result<RefMut<'_, lsp::Url, Mutex<tree_sitter::Tree>>> {
-        self.document_trees.get_mut(uri).ok_or_else(|| {
-            let kind = SessionResourceKind::Tree;
+    ) -> anyhow::Result<RefMut<'_, lsp::Url, crate::core::DocumentEntry>> {
+        self.documents.get_mut(uri).ok_or_else(|| {
+            let kind = SessionResourceKind::Document;
             let uri = uri.clone();
             crate::core::Error::SessionResourceNotFound { kind, uri }.into()
         })
     }
-
-    pub fn get_channel_syntax() -> anyhow::Result<web_sys::HtmlTextAreaElement> {
-        use wasm_bindgen::JsCast;
-        let element_id = "channel-syntax";
-        let channel_syntax = web_sys::window()
-            .ok_or_else(|| anyhow!("failed to get window"))?
-            .document()
-            .ok_or_else(|| anyhow!("failed to get document"))?
-            .get_element_by_id(element_id)
-            .ok_or_else(|| anyhow!("failed to get channel-syntax element"))?
-            .unchecked_into();
-        Ok(channel_syntax)
-    }
 }
@@ -0,0 +1,87 @@
+/// Which arguments get a hint naming their parameter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParameterNameHints {
+    None,
+    Literals,
+    All,
+}
+
+impl Default for ParameterNameHints {
+    fn default() -> Self {
+        ParameterNameHints::Literals
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InlayHintSettings {
+    pub parameter_names: ParameterNameHints,
+    pub destructured_defaults: bool,
+    pub array_indices: bool,
+}
+
+impl Default for InlayHintSettings {
+    fn default() -> Self {
+        InlayHintSettings {
+            parameter_names: ParameterNameHints::default(),
+            destructured_defaults: true,
+            array_indices: true,
+        }
+    }
+}
+
+/// Settings sent by the client in `initializationOptions` or `workspace/didChangeConfiguration`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Settings {
+    pub inlay_hints: InlayHintSettings,
+    /// Whether to send `demo/syntaxTree` notifications.
+    pub syntax_tree: bool,
+}
+
+impl Settings {
+    /// Update the settings from a JSON object, e.g., `{ "inlayHints": { "parameterNames": "all" } }`.
+    /// Settings which are missing or invalid are left unchanged.
+    pub fn update(&mut self, value: &serde_json::Value) {
+        if let Some(value) = value.get("syntaxTree").and_then(|value| value.as_bool()) {
+            self.syntax_tree = value;
+        }
+        if let Some(inlay_hints) = value.get("inlayHints") {
+            let settings = &mut self.inlay_hints;
+            match inlay_hints.get("parameterNames").and_then(|value| value.as_str()) {
+                Some("none") => settings.parameter_names = ParameterNameHints::None,
+                Some("literals") => settings.parameter_names = ParameterNameHints::Literals,
+                Some("all") => settings.parameter_names = ParameterNameHints::All,
+                _ => {},
+            }
+            if let Some(value) = inlay_hints.get("destructuredDefaults").and_then(|value| value.as_bool()) {
+                settings.destructured_defaults = value;
+            }
+            if let Some(value) = inlay_hints.get("arrayIndices").and_then(|value| value.as_bool()) {
+                settings.array_indices = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParameterNameHints, Settings};
+
+    #[test]
+    fn update() {
+        let mut settings = Settings::default();
+        settings.update(&serde_json::json!({
+            "inlayHints": {
+                "parameterNames": "all",
+                "arrayIndices": false,
+            },
+            "syntaxTree": true,
+        }));
+        assert_eq!(settings.inlay_hints.parameter_names, ParameterNameHints::All);
+        assert!(settings.inlay_hints.destructured_defaults);
+        assert!(!settings.inlay_hints.array_indices);
+        assert!(settings.syntax_tree);
+
+        settings.update(&serde_json::json!({ "inlayHints": { "parameterNames": "some" } }));
+        assert_eq!(settings.inlay_hints.parameter_names, ParameterNameHints::All);
+    }
+}
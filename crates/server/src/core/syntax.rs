@@ -1,20 +1,50 @@
-use crate::core::session::Session;
+/// The `demo/syntaxTree` notification, sent to clients which opt in with `"syntaxTree": true` in
+/// their `initializationOptions` whenever a document is parsed.
+pub enum SyntaxTreeNotification {}
 
-pub(crate) fn update_channel(tree: Option<&tree_sitter::Tree>) {
-    // assume errors; use red
-    let mut color = "rgb(255, 87, 51)";
-    if let Ok(channel_syntax) = Session::get_channel_syntax() {
-        if let Some(tree) = tree {
-            let sexp = crate::format_sexp(tree.root_node().to_sexp());
-            channel_syntax.set_value(&sexp);
-            if !tree.root_node().has_error() {
-                // no errors; use green
-                color = "rgb(218, 247, 166)";
-            }
+impl lsp::notification::Notification for SyntaxTreeNotification {
+    type Params = serde_json::Value;
+
+    const METHOD: &'static str = "demo/syntaxTree";
+}
+
+/// The syntax tree of a document after it was parsed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyntaxTree {
+    pub uri: lsp::Url,
+    pub version: i32,
+    /// The formatted S-expression of the tree.
+    pub sexp: String,
+    pub has_errors: bool,
+}
+
+impl SyntaxTree {
+    pub fn new(uri: lsp::Url, version: i32, tree: &tree_sitter::Tree) -> Self {
+        let root = tree.root_node();
+        let sexp = crate::format_sexp(root.to_sexp());
+        let has_errors = root.has_error();
+        SyntaxTree {
+            uri,
+            version,
+            sexp,
+            has_errors,
         }
-        channel_syntax
-            .style()
-            .set_property("background-color", color)
-            .expect("failed to set style");
     }
+
+    /// Return the parameters of the [`SyntaxTreeNotification`] for the tree.
+    pub fn params(&self) -> serde_json::Value {
+        serde_json::json!({
+            "uri": self.uri,
+            "version": self.version,
+            "sexp": self.sexp,
+            "hasErrors": self.has_errors,
+        })
+    }
+}
+
+/// A destination for the syntax trees of documents other than the client, e.g., a text area on the
+/// page hosting the server.
+#[tower_lsp::async_trait]
+pub trait SyntaxTreeSink: Send + Sync {
+    async fn update(&self, tree: &SyntaxTree);
 }
@@ -0,0 +1,35 @@
+//! Helpers for the tests which parse JavaScript, which is only possible with the native backend.
+
+use lsp_text::RopeExt;
+
+pub fn language() -> tree_sitter::Language {
+    futures::executor::block_on(crate::core::language::javascript()).unwrap()
+}
+
+/// Parse the source, returning the content and syntax tree of the document.
+pub fn parse(source: &str) -> (ropey::Rope, tree_sitter::Tree) {
+    let language = language();
+    let mut parser = crate::core::parser::javascript(&language).unwrap();
+    let tree = parser.parse(source, None).unwrap().unwrap();
+    (ropey::Rope::from(source), tree)
+}
+
+/// Return the position of the first occurrence of the needle in the content.
+pub fn position(content: &ropey::Rope, needle: &str) -> lsp::Position {
+    let byte = content.to_string().find(needle).unwrap();
+    content.byte_to_lsp_position(byte)
+}
+
+/// Apply the edits to the content, returning the resulting text.
+pub fn apply(content: &ropey::Rope, edits: &[lsp::TextEdit]) -> String {
+    let mut content = content.clone();
+    let mut edits = edits.to_vec();
+    edits.sort_by_key(|edit| std::cmp::Reverse((edit.range.start.line, edit.range.start.character)));
+    for edit in edits {
+        let start = content.lsp_position_to_core(edit.range.start).unwrap().char as usize;
+        let end = content.lsp_position_to_core(edit.range.end).unwrap().char as usize;
+        content.remove(start .. end);
+        content.insert(start, &edit.new_text);
+    }
+    content.to_string()
+}
@@ -1,3 +1,5 @@
+pub use lsp_text::TextEdit;
+
 pub struct Text {
     pub content: ropey::Rope,
 }
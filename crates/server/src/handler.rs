@@ -1,16 +1,29 @@
 pub mod text_document {
     use std::sync::Arc;
 
-    use lsp_text::RopeExt;
-
     pub async fn did_open(
         session: Arc<crate::core::Session>,
         params: lsp::DidOpenTextDocumentParams,
     ) -> anyhow::Result<()> {
         let uri = params.text_document.uri.clone();
+        let version = params.text_document.version;
+        let wants_syntax_tree = session.wants_syntax_trees().await;
+
+        let language_id = params.text_document.language_id.clone();
 
         if let Some(document) = crate::core::Document::open(session.clone(), params).await? {
+            let diagnostics = crate::provider::diagnostics::syntax(&document.content, &document.tree);
+            let symbols = outline(&session, &document.content, &document.tree)?;
+            let symbols = crate::provider::workspace_symbol::entries(&symbols);
+            let syntax_tree = wants_syntax_tree
+                .then(|| crate::core::syntax::SyntaxTree::new(uri.clone(), version, &document.tree));
+            session.workspace_symbols.insert(uri.clone(), symbols);
+            let document = crate::core::DocumentEntry::new(document, version, language_id);
             session.insert_document(uri.clone(), document)?;
+            session.client()?.publish_diagnostics(uri, diagnostics, Some(version)).await;
+            if let Some(syntax_tree) = syntax_tree {
+                publish_syntax_tree(&session, syntax_tree).await?;
+            }
         } else {
             log::warn!("'textDocument/didOpen' failed :: uri: {:#?}", uri);
         }
@@ -23,9 +36,90 @@ pub mod text_document {
         params: lsp::DidChangeTextDocumentParams,
     ) -> anyhow::Result<()> {
         let uri = &params.text_document.uri;
-        let mut text = session.get_mut_text(uri).await?;
-        *text = crate::core::Text::new(params.content_changes[0].text.clone())?;
-        crate::core::Document::change(session.clone(), uri, &text.content).await?;
+        let version = params.text_document.version;
+        let wants_syntax_tree = session.wants_syntax_trees().await;
+        let refresh_support = {
+            let capabilities = session.client_capabilities.read().await;
+            capabilities
+                .as_ref()
+                .and_then(|capabilities| capabilities.workspace.as_ref())
+                .and_then(|capabilities| capabilities.code_lens.as_ref())
+                .and_then(|capabilities| capabilities.refresh_support)
+                .unwrap_or_default()
+        };
+
+        // NOTE: the entry is changed while it is held exclusively, so concurrent requests see either
+        // the old text and tree or the new ones, but never a mix of both.
+        let (content, tree) = {
+            let mut document = session.get_mut_document(uri).await?;
+            // NOTE: versions only ever increase, so a change which is not newer than the document
+            // was either already applied or arrived out of order.
+            if version <= document.version {
+                log::warn!(
+                    "'textDocument/didChange' ignored :: uri: {:#?}, version: {}, current version: {}",
+                    uri,
+                    version,
+                    document.version
+                );
+                return Ok(());
+            }
+            let tree = document.change(version, &params.content_changes)?;
+            (document.text.content.clone(), tree)
+        };
+        let tree = match tree {
+            Some(tree) => tree,
+            None => {
+                log::warn!("'textDocument/didChange' failed :: uri: {:#?}", uri);
+                return Ok(());
+            },
+        };
+
+        // NOTE: the reference index is only rebuilt right away if lenses were resolved from it, to find
+        // out whether they need to be refreshed; otherwise it is built on the next resolve.
+        let previous_index = session.reference_index.remove(uri).map(|(_, index)| index);
+        let index = previous_index
+            .as_ref()
+            .filter(|_| refresh_support)
+            .map(|_| crate::provider::code_lens::Index::new(&content, &tree));
+        let symbols = outline(&session, &content, &tree)?;
+        let symbols = crate::provider::workspace_symbol::entries(&symbols);
+        let syntax_tree = wants_syntax_tree.then(|| crate::core::syntax::SyntaxTree::new(uri.clone(), version, &tree));
+        let diagnostics = crate::provider::diagnostics::syntax(&content, &tree);
+
+        // NOTE: a newer change may have been applied in the meantime, in which case the results for
+        // this version are left for that change to replace.
+        if session.ensure_version(uri, version).is_err() {
+            return Ok(());
+        }
+        session.workspace_symbols.insert(uri.clone(), symbols);
+        let lenses_changed = match (&previous_index, &index) {
+            (Some(previous_index), Some(index)) => index.changes_lenses(previous_index),
+            _ => false,
+        };
+        if let Some(index) = index {
+            session.reference_index.insert(uri.clone(), index);
+        }
+
+        if let Some(syntax_tree) = syntax_tree {
+            publish_syntax_tree(&session, syntax_tree).await?;
+        }
+
+        session
+            .client()?
+            .publish_diagnostics(uri.clone(), diagnostics, Some(version))
+            .await;
+
+        // NOTE: the refresh is not awaited, since the client may first ask for the lenses again and
+        // the notification should not be held up by that.
+        if lenses_changed {
+            let client = session.client()?.clone();
+            crate::spawn(async move {
+                if let Err(error) = client.code_lens_refresh().await {
+                    log::warn!("'workspace/codeLens/refresh' failed :: error: {:#?}", error);
+                }
+            });
+        }
+
         Ok(())
     }
 
@@ -41,128 +135,597 @@ pub mod text_document {
         Ok(())
     }
 
+    pub async fn goto_declaration(
+        session: Arc<crate::core::Session>,
+        params: lsp::request::GotoDeclarationParams,
+    ) -> anyhow::Result<Option<lsp::request::GotoDeclarationResponse>> {
+        // NOTE: bindings in JavaScript are declared and defined at the same place.
+        goto_definition(session, params).await
+    }
+
+    pub async fn goto_definition(
+        session: Arc<crate::core::Session>,
+        params: lsp::GotoDefinitionParams,
+    ) -> anyhow::Result<Option<lsp::GotoDefinitionResponse>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let location = crate::provider::definition::definition(uri, &content, &tree, position)?;
+        session.ensure_version(uri, version)?;
+        Ok(location.map(lsp::GotoDefinitionResponse::Scalar))
+    }
+
+    pub async fn references(
+        session: Arc<crate::core::Session>,
+        params: lsp::ReferenceParams,
+    ) -> anyhow::Result<Option<Vec<lsp::Location>>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let include_declaration = params.context.include_declaration;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let locations = crate::provider::references::references(uri, &content, &tree, position, include_declaration)?;
+        session.ensure_version(uri, version)?;
+        Ok(locations)
+    }
+
+    pub async fn document_highlight(
+        session: Arc<crate::core::Session>,
+        params: lsp::DocumentHighlightParams,
+    ) -> anyhow::Result<Option<Vec<lsp::DocumentHighlight>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let highlights = crate::provider::document_highlight::document_highlight(&content, &tree, position)?;
+        session.ensure_version(uri, version)?;
+        Ok(highlights)
+    }
+
+    pub async fn completion(
+        session: Arc<crate::core::Session>,
+        params: lsp::CompletionParams,
+    ) -> anyhow::Result<Option<lsp::CompletionResponse>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        let options = {
+            let capabilities = session.client_capabilities.read().await;
+            crate::provider::completion::Options::new(capabilities.as_ref())
+        };
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let completions = crate::provider::completion::completion(uri, &content, &tree, position, options)?;
+        session.ensure_version(uri, version)?;
+        Ok(completions)
+    }
+
+    pub async fn completion_resolve(
+        session: Arc<crate::core::Session>,
+        params: lsp::CompletionItem,
+    ) -> anyhow::Result<lsp::CompletionItem> {
+        let uri = match crate::provider::completion::data_uri(&params) {
+            Some(uri) => uri,
+            None => return Ok(params),
+        };
+
+        let options = {
+            let capabilities = session.client_capabilities.read().await;
+            crate::provider::completion::Options::new(capabilities.as_ref())
+        };
+
+        let (content, tree, version) = snapshot(&session, &uri).await?;
+
+        let item = crate::provider::completion::resolve(&content, &tree, params, options);
+        session.ensure_version(&uri, version)?;
+        Ok(item)
+    }
+
+    pub async fn hover(
+        session: Arc<crate::core::Session>,
+        params: lsp::HoverParams,
+    ) -> anyhow::Result<Option<lsp::Hover>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let markdown = {
+            let capabilities = session.client_capabilities.read().await;
+            crate::provider::hover::supports_markdown(capabilities.as_ref())
+        };
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let hover = crate::provider::hover::hover(&content, &tree, position, markdown)?;
+        session.ensure_version(uri, version)?;
+        Ok(hover)
+    }
+
+    pub async fn signature_help(
+        session: Arc<crate::core::Session>,
+        params: lsp::SignatureHelpParams,
+    ) -> anyhow::Result<Option<lsp::SignatureHelp>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let options = {
+            let capabilities = session.client_capabilities.read().await;
+            crate::provider::signature_help::Options::new(capabilities.as_ref())
+        };
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let help = crate::provider::signature_help::signature_help(&content, &tree, position, options)?;
+        session.ensure_version(uri, version)?;
+        Ok(help)
+    }
+
+    pub async fn code_action(
+        session: Arc<crate::core::Session>,
+        params: lsp::CodeActionParams,
+    ) -> anyhow::Result<Option<lsp::CodeActionResponse>> {
+        let uri = &params.text_document.uri;
+
+        let options = {
+            let capabilities = session.client_capabilities.read().await;
+            crate::provider::code_action::Options::new(capabilities.as_ref())
+        };
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let range = params.range;
+        let context = &params.context;
+        let actions = crate::provider::code_action::code_actions(uri, &content, &tree, range, context, options)?;
+        session.ensure_version(uri, version)?;
+        Ok(Some(actions))
+    }
+
+    pub async fn code_action_resolve(
+        session: Arc<crate::core::Session>,
+        params: lsp::CodeAction,
+    ) -> anyhow::Result<lsp::CodeAction> {
+        let uri = match crate::provider::code_action::data_uri(&params) {
+            Some(uri) => uri,
+            None => return Ok(params),
+        };
+
+        let (content, tree, version) = snapshot(&session, &uri).await?;
+
+        let action = crate::provider::code_action::resolve(&content, &tree, params)?;
+        session.ensure_version(&uri, version)?;
+        Ok(action)
+    }
+
+    pub async fn code_lens(
+        session: Arc<crate::core::Session>,
+        params: lsp::CodeLensParams,
+    ) -> anyhow::Result<Option<Vec<lsp::CodeLens>>> {
+        let uri = &params.text_document.uri;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let lenses = crate::provider::code_lens::code_lenses(uri, &content, &tree);
+        session.ensure_version(uri, version)?;
+        Ok(Some(lenses))
+    }
+
+    pub async fn code_lens_resolve(
+        session: Arc<crate::core::Session>,
+        params: lsp::CodeLens,
+    ) -> anyhow::Result<lsp::CodeLens> {
+        let (uri, offset) = match crate::provider::code_lens::data(&params) {
+            Some(data) => data,
+            None => return Ok(params),
+        };
+
+        // NOTE: the index is built on the first resolve after a change and shared by the other
+        // lenses of the document.
+        if !session.reference_index.contains_key(&uri) {
+            let (content, tree, version) = snapshot(&session, &uri).await?;
+            let index = crate::provider::code_lens::Index::new(&content, &tree);
+            session.ensure_version(&uri, version)?;
+            session.reference_index.insert(uri.clone(), index);
+        }
+        let lens = match session.reference_index.get(&uri) {
+            Some(index) => crate::provider::code_lens::resolve(&uri, &index, offset, params),
+            None => params,
+        };
+        Ok(lens)
+    }
+
+    pub async fn prepare_call_hierarchy(
+        session: Arc<crate::core::Session>,
+        params: lsp::CallHierarchyPrepareParams,
+    ) -> anyhow::Result<Option<Vec<lsp::CallHierarchyItem>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+        let source = crate::provider::call_hierarchy::Source {
+            uri: uri.clone(),
+            version,
+            content,
+            tree,
+        };
+
+        let items = crate::provider::call_hierarchy::prepare(&source, position)?;
+        session.ensure_version(uri, version)?;
+        Ok(items)
+    }
+
+    pub async fn incoming_calls(
+        session: Arc<crate::core::Session>,
+        params: lsp::CallHierarchyIncomingCallsParams,
+    ) -> anyhow::Result<Option<Vec<lsp::CallHierarchyIncomingCall>>> {
+        let sources = call_hierarchy_sources(&session).await?;
+        let calls = crate::provider::call_hierarchy::incoming_calls(&sources, &params.item);
+        ensure_sources_current(&session, &sources)?;
+        Ok(Some(calls))
+    }
+
+    pub async fn outgoing_calls(
+        session: Arc<crate::core::Session>,
+        params: lsp::CallHierarchyOutgoingCallsParams,
+    ) -> anyhow::Result<Option<Vec<lsp::CallHierarchyOutgoingCall>>> {
+        let sources = call_hierarchy_sources(&session).await?;
+        let calls = crate::provider::call_hierarchy::outgoing_calls(&sources, &params.item);
+        ensure_sources_current(&session, &sources)?;
+        Ok(Some(calls))
+    }
+
+    /// Collect the content and tree of every open document.
+    async fn call_hierarchy_sources(
+        session: &crate::core::Session,
+    ) -> anyhow::Result<Vec<crate::provider::call_hierarchy::Source>> {
+        let mut sources = vec![];
+        for uri in session.document_uris() {
+            let (content, tree, version) = snapshot(session, &uri).await?;
+            sources.push(crate::provider::call_hierarchy::Source {
+                uri,
+                version,
+                content,
+                tree,
+            });
+        }
+        Ok(sources)
+    }
+
+    /// Return an error if any of the documents changed since its source was collected.
+    fn ensure_sources_current(
+        session: &crate::core::Session,
+        sources: &[crate::provider::call_hierarchy::Source],
+    ) -> anyhow::Result<()> {
+        for source in sources {
+            session.ensure_version(&source.uri, source.version)?;
+        }
+        Ok(())
+    }
+
+    pub async fn inlay_hint(
+        session: Arc<crate::core::Session>,
+        params: lsp::InlayHintParams,
+    ) -> anyhow::Result<Option<Vec<lsp::InlayHint>>> {
+        let uri = &params.text_document.uri;
+
+        let settings = session.settings.read().await.inlay_hints;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let hints = crate::provider::inlay_hint::inlay_hints(uri, &content, &tree, params.range, settings)?;
+        session.ensure_version(uri, version)?;
+        Ok(Some(hints))
+    }
+
+    pub async fn inlay_hint_resolve(
+        session: Arc<crate::core::Session>,
+        params: lsp::InlayHint,
+    ) -> anyhow::Result<lsp::InlayHint> {
+        let uri = match crate::provider::inlay_hint::data_uri(&params) {
+            Some(uri) => uri,
+            None => return Ok(params),
+        };
+
+        let markdown = {
+            let capabilities = session.client_capabilities.read().await;
+            crate::provider::hover::supports_markdown(capabilities.as_ref())
+        };
+
+        let (content, tree, version) = snapshot(&session, &uri).await?;
+
+        let hint = crate::provider::inlay_hint::resolve(&content, &tree, params, markdown);
+        session.ensure_version(&uri, version)?;
+        Ok(hint)
+    }
+
     pub async fn document_symbol(
         session: Arc<crate::core::Session>,
         params: lsp::DocumentSymbolParams,
     ) -> anyhow::Result<Option<lsp::DocumentSymbolResponse>> {
-        use wasm_bindgen::JsCast;
-
-        fn make_symbol(
-            uri: &lsp::Url,
-            content: &ropey::Rope,
-            declaration: tree_sitter::Node,
-            identifier: tree_sitter::Node,
-            kind: lsp::SymbolKind,
-        ) -> lsp::SymbolInformation {
-            let name = content.utf8_text_for_tree_sitter_node(&identifier).into();
-            let range = content.tree_sitter_range_to_lsp_range(declaration.range());
-            #[allow(deprecated)]
-            lsp::SymbolInformation {
-                name,
-                kind,
-                tags: Default::default(),
-                deprecated: Default::default(),
-                location: lsp::Location::new(uri.clone(), range),
-                container_name: Default::default(),
-            }
+        let uri = &params.text_document.uri;
+
+        let hierarchical = session
+            .client_capabilities
+            .read()
+            .await
+            .as_ref()
+            .and_then(|capabilities| capabilities.text_document.as_ref())
+            .and_then(|capabilities| capabilities.document_symbol.as_ref())
+            .and_then(|capabilities| capabilities.hierarchical_document_symbol_support)
+            .unwrap_or_default();
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let symbols = outline(&session, &content, &tree)?;
+        session.ensure_version(uri, version)?;
+
+        if hierarchical {
+            Ok(Some(lsp::DocumentSymbolResponse::Nested(symbols)))
+        } else {
+            let symbols = crate::provider::document_symbol::flat(uri, symbols);
+            Ok(Some(lsp::DocumentSymbolResponse::Flat(symbols)))
         }
+    }
 
+    pub async fn prepare_rename(
+        session: Arc<crate::core::Session>,
+        params: lsp::TextDocumentPositionParams,
+    ) -> anyhow::Result<Option<lsp::PrepareRenameResponse>> {
         let uri = &params.text_document.uri;
 
-        let text = session.get_text(uri).await?;
-        let content = &text.content;
-
-        let tree = session.get_tree(uri).await?;
-        let tree = tree.lock().await.clone();
-
-        // NOTE: transmutes here because we do not yet support query functionality in
-        // tree-sitter-facade; thus we use the raw bindings from web-tree-sitter-sys.
-
-        #[allow(unsafe_code)]
-        let node = unsafe { std::mem::transmute::<_, web_tree_sitter_sys::SyntaxNode>(tree.root_node()) };
-        #[allow(unsafe_code)]
-        let language = unsafe { std::mem::transmute::<_, web_tree_sitter_sys::Language>(session.language.clone()) };
-
-        static QUERY: &str = indoc::indoc! {r"
-          (function_declaration
-            name: (identifier) @identifier) @function_declaration
-          (lexical_declaration
-            (variable_declarator
-              name: (identifier) @identifier)) @class_declaration
-          (variable_declaration
-            (variable_declarator
-              name: (identifier) @identifier)) @variable_declaration
-          (class_declaration
-            name: (identifier) @identifier) @class_declaration
-        "};
-        let query = language.query(&QUERY.into()).expect("failed to create query");
-        let matches = {
-            let start_position = None;
-            let end_position = None;
-            query
-                .matches(&node, start_position, end_position)
-                .into_vec()
-                .into_iter()
-                .map(JsCast::unchecked_into::<web_tree_sitter_sys::QueryMatch>)
-        };
-
-        let mut symbols = vec![];
-
-        for r#match in matches {
-            let captures = r#match
-                .captures()
-                .into_vec()
-                .into_iter()
-                .map(JsCast::unchecked_into::<web_tree_sitter_sys::QueryCapture>)
-                .collect::<Vec<_>>();
-            if let [declaration, identifier] = captures.as_slice() {
-                // NOTE: reverse the transmutes from above so we can use tree-sitter-facade bindings for Node
-                #[allow(unsafe_code)]
-                let declaration_node = unsafe { std::mem::transmute::<_, tree_sitter::Node>(declaration.node()) };
-                #[allow(unsafe_code)]
-                let identifier_node = unsafe { std::mem::transmute::<_, tree_sitter::Node>(identifier.node()) };
-                match String::from(declaration.name()).as_str() {
-                    "function_declaration" => {
-                        symbols.push(make_symbol(
-                            uri,
-                            content,
-                            declaration_node,
-                            identifier_node,
-                            lsp::SymbolKind::FUNCTION,
-                        ));
-                    },
-                    "lexical_declaration" => {
-                        symbols.push(make_symbol(
-                            uri,
-                            content,
-                            declaration_node,
-                            identifier_node,
-                            lsp::SymbolKind::VARIABLE,
-                        ));
-                    },
-                    "variable_declaration" => {
-                        symbols.push(make_symbol(
-                            uri,
-                            content,
-                            declaration_node,
-                            identifier_node,
-                            lsp::SymbolKind::VARIABLE,
-                        ));
-                    },
-                    "class_declaration" => {
-                        symbols.push(make_symbol(
-                            uri,
-                            content,
-                            declaration_node,
-                            identifier_node,
-                            lsp::SymbolKind::VARIABLE,
-                        ));
-                    },
-                    _ => {},
-                }
-            }
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let response = crate::provider::rename::prepare_rename(&content, &tree, params.position)?;
+        session.ensure_version(uri, version)?;
+        Ok(response)
+    }
+
+    pub async fn rename(
+        session: Arc<crate::core::Session>,
+        params: lsp::RenameParams,
+    ) -> anyhow::Result<Option<lsp::WorkspaceEdit>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let edit = crate::provider::rename::rename(uri, &content, &tree, position, &params.new_name)?;
+        session.ensure_version(uri, version)?;
+        Ok(edit)
+    }
+
+    pub async fn formatting(
+        session: Arc<crate::core::Session>,
+        params: lsp::DocumentFormattingParams,
+    ) -> anyhow::Result<Option<Vec<lsp::TextEdit>>> {
+        let uri = &params.text_document.uri;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let edits = crate::provider::formatting::formatting(&content, &tree, &params.options, None)?;
+        session.ensure_version(uri, version)?;
+        Ok(Some(edits))
+    }
+
+    pub async fn range_formatting(
+        session: Arc<crate::core::Session>,
+        params: lsp::DocumentRangeFormattingParams,
+    ) -> anyhow::Result<Option<Vec<lsp::TextEdit>>> {
+        let uri = &params.text_document.uri;
+        let range = Some(params.range);
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let edits = crate::provider::formatting::formatting(&content, &tree, &params.options, range)?;
+        session.ensure_version(uri, version)?;
+        Ok(Some(edits))
+    }
+
+    pub async fn on_type_formatting(
+        session: Arc<crate::core::Session>,
+        params: lsp::DocumentOnTypeFormattingParams,
+    ) -> anyhow::Result<Option<Vec<lsp::TextEdit>>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let ch = &params.ch;
+        let options = &params.options;
+        let edits = crate::provider::on_type_formatting::on_type_formatting(&content, &tree, position, ch, options)?;
+        session.ensure_version(uri, version)?;
+        Ok(edits)
+    }
+
+    pub async fn folding_range(
+        session: Arc<crate::core::Session>,
+        params: lsp::FoldingRangeParams,
+    ) -> anyhow::Result<Option<Vec<lsp::FoldingRange>>> {
+        let uri = &params.text_document.uri;
+
+        let options = {
+            let capabilities = session.client_capabilities.read().await;
+            crate::provider::folding_range::Options::new(capabilities.as_ref())
+        };
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let ranges = crate::provider::folding_range::folding_ranges(&content, &tree, options);
+        session.ensure_version(uri, version)?;
+        Ok(Some(ranges))
+    }
+
+    pub async fn selection_range(
+        session: Arc<crate::core::Session>,
+        params: lsp::SelectionRangeParams,
+    ) -> anyhow::Result<Option<Vec<lsp::SelectionRange>>> {
+        let uri = &params.text_document.uri;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        let ranges = crate::provider::selection_range::selection_ranges(&content, &tree, params.positions)?;
+        session.ensure_version(uri, version)?;
+        Ok(Some(ranges))
+    }
+
+    pub async fn semantic_tokens_full(
+        session: Arc<crate::core::Session>,
+        params: lsp::SemanticTokensParams,
+    ) -> anyhow::Result<Option<lsp::SemanticTokensResult>> {
+        let uri = &params.text_document.uri;
+        let (result_id, data) = semantic_tokens_current(session, uri).await?;
+        let result_id = Some(result_id);
+        let tokens = lsp::SemanticTokens { result_id, data };
+        Ok(Some(lsp::SemanticTokensResult::Tokens(tokens)))
+    }
+
+    pub async fn semantic_tokens_full_delta(
+        session: Arc<crate::core::Session>,
+        params: lsp::SemanticTokensDeltaParams,
+    ) -> anyhow::Result<Option<lsp::SemanticTokensFullDeltaResult>> {
+        let uri = &params.text_document.uri;
+
+        // NOTE: the previous tokens must be captured before they are replaced by the current ones.
+        let previous = session
+            .semantic_tokens
+            .get(uri)
+            .filter(|cache| cache.result_id() == params.previous_result_id)
+            .map(|cache| cache.tokens.clone());
+
+        let (result_id, data) = semantic_tokens_current(session, uri).await?;
+        let result_id = Some(result_id);
+
+        if let Some(previous) = previous {
+            let edits = crate::provider::semantic_tokens::edits(&previous, &data);
+            let delta = lsp::SemanticTokensDelta { result_id, edits };
+            Ok(Some(lsp::SemanticTokensFullDeltaResult::TokensDelta(delta)))
+        } else {
+            let tokens = lsp::SemanticTokens { result_id, data };
+            Ok(Some(lsp::SemanticTokensFullDeltaResult::Tokens(tokens)))
         }
+    }
+
+    pub async fn semantic_tokens_range(
+        session: Arc<crate::core::Session>,
+        params: lsp::SemanticTokensRangeParams,
+    ) -> anyhow::Result<Option<lsp::SemanticTokensRangeResult>> {
+        let uri = &params.text_document.uri;
+
+        let legend = session
+            .semantic_tokens_legend()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("semantic tokens legend not found"))?;
+
+        let (content, tree, version) = snapshot(&session, uri).await?;
 
-        Ok(Some(lsp::DocumentSymbolResponse::Flat(symbols)))
+        let range = Some(params.range);
+        let data = crate::provider::semantic_tokens::tokens(&legend, &content, &tree, range);
+        session.ensure_version(uri, version)?;
+        let result_id = Default::default();
+        let tokens = lsp::SemanticTokens { result_id, data };
+        Ok(Some(lsp::SemanticTokensRangeResult::Tokens(tokens)))
+    }
+
+    /// Send the syntax tree of a document to the client, if it opted in, and to the sink of the
+    /// session, if any.
+    async fn publish_syntax_tree(
+        session: &crate::core::Session,
+        syntax_tree: crate::core::syntax::SyntaxTree,
+    ) -> anyhow::Result<()> {
+        if session.settings.read().await.syntax_tree {
+            let params = syntax_tree.params();
+            session
+                .client()?
+                .send_notification::<crate::core::syntax::SyntaxTreeNotification>(params)
+                .await;
+        }
+        if let Some(sink) = &session.syntax_tree_sink {
+            sink.update(&syntax_tree).await;
+        }
+        Ok(())
+    }
+
+    /// Copy the content, tree and version of a document out of the session, so that requests are
+    /// computed without holding the entry and can check afterwards whether the document changed.
+    async fn snapshot(
+        session: &crate::core::Session,
+        uri: &lsp::Url,
+    ) -> anyhow::Result<(ropey::Rope, tree_sitter::Tree, i32)> {
+        let document = session.get_document(uri).await?;
+        Ok((document.text.content.clone(), document.tree.clone(), document.version))
+    }
+
+    /// Compute the symbol outline of a document.
+    fn outline(
+        session: &crate::core::Session,
+        content: &ropey::Rope,
+        tree: &tree_sitter::Tree,
+    ) -> anyhow::Result<Vec<lsp::DocumentSymbol>> {
+        let query = session.query(crate::provider::document_symbol::QUERY)?;
+        let declarations = crate::provider::document_symbol::declarations(&query, content, tree);
+        Ok(crate::provider::document_symbol::nested(content, declarations))
+    }
+
+    /// Return the semantic tokens for the current content of the document, reusing the cached
+    /// tokens when they were computed for the current version of the document.
+    async fn semantic_tokens_current(
+        session: Arc<crate::core::Session>,
+        uri: &lsp::Url,
+    ) -> anyhow::Result<(String, Vec<lsp::SemanticToken>)> {
+        let (content, tree, version) = snapshot(&session, uri).await?;
+
+        if let Some(cache) = session.semantic_tokens.get(uri).filter(|cache| cache.version == version) {
+            return Ok((cache.result_id(), cache.tokens.clone()));
+        }
+
+        let legend = session
+            .semantic_tokens_legend()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("semantic tokens legend not found"))?;
+
+        let range = None;
+        let tokens = crate::provider::semantic_tokens::tokens(&legend, &content, &tree, range);
+
+        // NOTE: tokens for an outdated version must not replace those of a newer one.
+        session.ensure_version(uri, version)?;
+
+        let cache = crate::provider::semantic_tokens::Cache {
+            version,
+            tokens: tokens.clone(),
+        };
+        let result_id = cache.result_id();
+        session.semantic_tokens.insert(uri.clone(), cache);
+
+        Ok((result_id, tokens))
+    }
+}
+
+pub mod workspace {
+    use std::sync::Arc;
+
+    pub async fn did_change_configuration(
+        session: Arc<crate::core::Session>,
+        params: lsp::DidChangeConfigurationParams,
+    ) -> anyhow::Result<()> {
+        session.settings.write().await.update(&params.settings);
+        Ok(())
+    }
+
+    pub async fn symbol(
+        session: Arc<crate::core::Session>,
+        params: lsp::WorkspaceSymbolParams,
+    ) -> anyhow::Result<Option<Vec<lsp::SymbolInformation>>> {
+        let symbols = crate::provider::workspace_symbol::symbols(&session.workspace_symbols, &params.query);
+        Ok(Some(symbols))
+    }
+
+    pub async fn symbol_resolve(
+        session: Arc<crate::core::Session>,
+        params: lsp::WorkspaceSymbol,
+    ) -> anyhow::Result<lsp::WorkspaceSymbol> {
+        Ok(crate::provider::workspace_symbol::resolve(&session.workspace_symbols, params))
     }
 }
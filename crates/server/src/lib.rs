@@ -1,12 +1,35 @@
 #![deny(clippy::all)]
 #![deny(unsafe_code)]
 
+#[cfg(all(feature = "wasm", feature = "native"))]
+compile_error!("the `wasm` and `native` features are mutually exclusive");
+
 mod core;
 pub mod handler;
+mod provider;
 mod server;
 
+pub use crate::core::syntax::{SyntaxTree, SyntaxTreeSink};
 pub use server::*;
 
+/// Log a trace message to the browser console or, for the native server, at the trace level of
+/// `log` since stdout carries the protocol.
+pub(crate) fn trace(message: &str) {
+    #[cfg(feature = "wasm")]
+    web_sys::console::log_1(&message.into());
+    #[cfg(feature = "native")]
+    log::trace!("{}", message);
+}
+
+/// Run a future in the background, on the event loop of the browser or, for the native server, on
+/// the tokio runtime.
+pub(crate) fn spawn(future: impl std::future::Future<Output = ()> + Send + 'static) {
+    #[cfg(feature = "wasm")]
+    wasm_bindgen_futures::spawn_local(future);
+    #[cfg(feature = "native")]
+    tokio::spawn(future);
+}
+
 pub(crate) fn format_sexp(sexp: impl AsRef<str>) -> String {
     format_sexp_indented(sexp, 0)
 }
@@ -0,0 +1,378 @@
+use crate::core::{declaration, scope::Scopes};
+use lsp_text::RopeExt;
+
+/// An open document searched for calls.
+pub struct Source {
+    pub uri: lsp::Url,
+    /// The version of the document which the content and tree belong to.
+    pub version: i32,
+    pub content: ropey::Rope,
+    pub tree: tree_sitter::Tree,
+}
+
+impl Source {
+    fn text(&self, node: &tree_sitter::Node) -> String {
+        self.content.utf8_text_for_tree_sitter_node(node).to_string()
+    }
+
+    fn range(&self, node: &tree_sitter::Node) -> lsp::Range {
+        self.content.tree_sitter_range_to_lsp_range(node.range())
+    }
+}
+
+/// Return the item for the function, method or class named at the position, or called by the
+/// call whose callee is at the position.
+pub fn prepare(source: &Source, position: lsp::Position) -> anyhow::Result<Option<Vec<lsp::CallHierarchyItem>>> {
+    let byte = source.content.lsp_position_to_core(position)?.byte;
+    let node = match source.tree.root_node().named_descendant_for_byte_range(byte, byte) {
+        Some(node) => node,
+        None => return Ok(None),
+    };
+    let parent = node.parent();
+
+    // NOTE: the name of a declaration denotes the declaration itself.
+    let declared = parent.clone().filter(|parent| {
+        let is_name = name(parent).map_or(false, |name| name == node);
+        is_name && (declaration::is_function(parent) || is_class(parent) || is_function_declarator(parent))
+    });
+    let target = match declared {
+        Some(parent) if parent.kind() == "variable_declarator" => parent.child_by_field_name("value"),
+        // NOTE: calls to a class resolve to its constructor, if it has one.
+        Some(parent) if is_class(&parent) => Some(constructor(&source.content, &parent).unwrap_or(parent)),
+        Some(parent) => Some(parent),
+        None => {
+            let scopes = Scopes::analyze(&source.content, &source.tree);
+            let callee = match parent {
+                Some(parent) if parent.kind() == "member_expression" => parent,
+                _ => node,
+            };
+            resolve(source, &scopes, &callee)
+        },
+    };
+
+    Ok(target.map(|target| vec![item(source, &target)]))
+}
+
+/// Collect the calls to the item from every source, grouped by the function containing them.
+pub fn incoming_calls(sources: &[Source], item: &lsp::CallHierarchyItem) -> Vec<lsp::CallHierarchyIncomingCall> {
+    let target = match sources.iter().find(|source| source.uri == item.uri) {
+        Some(source) => find(source, item).map(|node| (source, node)),
+        None => None,
+    };
+    let (target_source, target) = match target {
+        Some(target) => target,
+        None => return vec![],
+    };
+
+    let mut groups: Vec<(tree_sitter::Node, &Source, Vec<lsp::Range>)> = vec![];
+    for source in sources {
+        let scopes = Scopes::analyze(&source.content, &source.tree);
+        for call in calls(&source.tree.root_node(), true) {
+            let callee = match call.child_by_field_name(callee_field(&call)) {
+                Some(callee) => callee,
+                None => continue,
+            };
+            // NOTE: calls in other documents are only resolved within their own document, so a
+            // call which does not resolve there is attributed by name.
+            let is_call = match resolve(source, &scopes, &callee) {
+                Some(resolved) => std::ptr::eq(source, target_source) && resolved == target,
+                None => {
+                    let name = callee_name(source, &callee);
+                    !std::ptr::eq(source, target_source) && name.as_deref() == Some(item.name.as_str())
+                },
+            };
+            if !is_call {
+                continue;
+            }
+            let caller = caller(&call);
+            let range = source.range(&callee);
+            match groups
+                .iter_mut()
+                .find(|(other, other_source, _)| *other == caller && std::ptr::eq(*other_source, source))
+            {
+                Some((_, _, ranges)) => ranges.push(range),
+                None => groups.push((caller, source, vec![range])),
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(caller, source, from_ranges)| lsp::CallHierarchyIncomingCall {
+            from: self::item(source, &caller),
+            from_ranges,
+        })
+        .collect()
+}
+
+/// Collect the calls made by the item, grouped by the function they call. Calls which cannot be
+/// resolved are reported as unresolved items.
+pub fn outgoing_calls(sources: &[Source], item: &lsp::CallHierarchyItem) -> Vec<lsp::CallHierarchyOutgoingCall> {
+    let source = match sources.iter().find(|source| source.uri == item.uri) {
+        Some(source) => source,
+        None => return vec![],
+    };
+    let caller = match find(source, item) {
+        Some(caller) => caller,
+        None => return vec![],
+    };
+    let scopes = Scopes::analyze(&source.content, &source.tree);
+
+    let mut groups: Vec<(lsp::CallHierarchyItem, Vec<lsp::Range>)> = vec![];
+    for call in calls(&caller, false) {
+        let callee = match call.child_by_field_name(callee_field(&call)) {
+            Some(callee) => callee,
+            None => continue,
+        };
+        let to = match resolve(source, &scopes, &callee) {
+            Some(target) => self::item(source, &target),
+            None => callee_name(source, &callee)
+                .and_then(|name| declared_elsewhere(sources, source, &name))
+                .unwrap_or_else(|| unresolved(source, &call, &callee)),
+        };
+        let range = source.range(&callee);
+        match groups.iter_mut().find(|(other, _)| is_same_item(other, &to)) {
+            Some((_, ranges)) => ranges.push(range),
+            None => groups.push((to, vec![range])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(to, from_ranges)| lsp::CallHierarchyOutgoingCall { to, from_ranges })
+        .collect()
+}
+
+fn callee_field(call: &tree_sitter::Node) -> &'static str {
+    if call.kind() == "new_expression" {
+        "constructor"
+    } else {
+        "function"
+    }
+}
+
+fn is_class(node: &tree_sitter::Node) -> bool {
+    matches!(&*node.kind(), "class_declaration" | "class")
+}
+
+/// Return the constructor of a class.
+fn constructor<'tree>(content: &ropey::Rope, class: &tree_sitter::Node<'tree>) -> Option<tree_sitter::Node<'tree>> {
+    let body = class.child_by_field_name("body")?;
+    (0 .. body.named_child_count())
+        .filter_map(|i| body.named_child(i))
+        .filter(|member| member.kind() == "method_definition")
+        .find(|member| {
+            member
+                .child_by_field_name("name")
+                .map_or(false, |name| content.utf8_text_for_tree_sitter_node(&name) == "constructor")
+        })
+}
+
+/// Whether the node is a variable declarator whose value is a function, e.g., `const f = () => {}`.
+fn is_function_declarator(node: &tree_sitter::Node) -> bool {
+    node.kind() == "variable_declarator"
+        && node
+            .child_by_field_name("value")
+            .map_or(false, |value| declaration::is_function(&value))
+}
+
+/// Return the node naming a function, method, class or function-valued declarator.
+fn name<'tree>(node: &tree_sitter::Node<'tree>) -> Option<tree_sitter::Node<'tree>> {
+    if let Some(name) = node.child_by_field_name("name") {
+        return Some(name);
+    }
+    // NOTE: anonymous functions are named after what they are assigned to.
+    let parent = node.parent()?;
+    match &*parent.kind() {
+        "variable_declarator" => parent.child_by_field_name("name"),
+        "pair" => parent.child_by_field_name("key"),
+        "assignment_expression" => parent.child_by_field_name("left"),
+        _ => None,
+    }
+}
+
+/// Resolve a callee (or the name of a function) to the declaration of the function, method or
+/// class it refers to.
+fn resolve<'tree>(
+    source: &'tree Source,
+    scopes: &Scopes,
+    callee: &tree_sitter::Node<'tree>,
+) -> Option<tree_sitter::Node<'tree>> {
+    if let Some(callee) = declaration::callee(&source.content, &source.tree, scopes, callee) {
+        return Some(callee.function);
+    }
+    // NOTE: a class without a constructor is still the target of `new`.
+    if callee.kind() == "identifier" {
+        let binding = &scopes.bindings[scopes.binding_at(callee.start_byte())?];
+        return declaration::node(&source.tree, binding).filter(is_class);
+    }
+    None
+}
+
+/// The name a callee calls, e.g., `f` for `f()` and `m` for `a.b.m()`.
+fn callee_name(source: &Source, callee: &tree_sitter::Node) -> Option<String> {
+    match &*callee.kind() {
+        "identifier" => Some(source.text(callee)),
+        "member_expression" => callee
+            .child_by_field_name("property")
+            .map(|property| source.text(&property)),
+        _ => None,
+    }
+}
+
+/// Return the innermost function, method or class containing the call, or the program for calls
+/// at the top level.
+fn caller<'tree>(call: &tree_sitter::Node<'tree>) -> tree_sitter::Node<'tree> {
+    let mut node = call.clone();
+    while let Some(parent) = node.parent() {
+        if declaration::is_function(&parent) {
+            return parent;
+        }
+        node = parent;
+    }
+    node
+}
+
+/// Collect the call and `new` expressions within the node, excluding those in nested functions
+/// unless `nested` is set.
+fn calls<'tree>(node: &tree_sitter::Node<'tree>, nested: bool) -> Vec<tree_sitter::Node<'tree>> {
+    fn visit<'tree>(node: &tree_sitter::Node<'tree>, nested: bool, calls: &mut Vec<tree_sitter::Node<'tree>>) {
+        for i in 0 .. node.named_child_count() {
+            let child = match node.named_child(i) {
+                Some(child) => child,
+                None => continue,
+            };
+            if matches!(&*child.kind(), "call_expression" | "new_expression") {
+                calls.push(child.clone());
+            }
+            if nested || !declaration::is_function(&child) {
+                visit(&child, nested, calls);
+            }
+        }
+    }
+
+    let mut calls = vec![];
+    visit(node, nested, &mut calls);
+    calls
+}
+
+/// Find the top-level function or class with the given name in the sources other than `except`.
+fn declared_elsewhere(sources: &[Source], except: &Source, name: &str) -> Option<lsp::CallHierarchyItem> {
+    sources
+        .iter()
+        .filter(|source| !std::ptr::eq(*source, except))
+        .find_map(|source| {
+            top_level_functions(&source.tree)
+                .into_iter()
+                .find(|function| self::name(function).map(|name| source.text(&name)).as_deref() == Some(name))
+                .map(|function| item(source, &function))
+        })
+}
+
+/// Collect the functions and classes declared at the top level, including exported ones.
+fn top_level_functions(tree: &tree_sitter::Tree) -> Vec<tree_sitter::Node> {
+    let root = tree.root_node();
+    let mut functions = vec![];
+    for i in 0 .. root.named_child_count() {
+        let statement = match root.named_child(i) {
+            Some(statement) if statement.kind() == "export_statement" => statement.child_by_field_name("declaration"),
+            statement => statement,
+        };
+        let statement = match statement {
+            Some(statement) => statement,
+            None => continue,
+        };
+        if declaration::is_function(&statement) || is_class(&statement) {
+            functions.push(statement);
+        } else if matches!(&*statement.kind(), "lexical_declaration" | "variable_declaration") {
+            let values = (0 .. statement.named_child_count())
+                .filter_map(|i| statement.named_child(i))
+                .filter(is_function_declarator)
+                .filter_map(|declarator| declarator.child_by_field_name("value"));
+            functions.extend(values);
+        }
+    }
+    functions
+}
+
+/// Build the item for a function, method, class or program node.
+fn item(source: &Source, node: &tree_sitter::Node) -> lsp::CallHierarchyItem {
+    let name_node = self::name(node).filter(|_| node.kind() != "program");
+    let is_constructor = name_node.as_ref().map_or(false, |name| source.text(name) == "constructor");
+    // NOTE: a constructor is named after its class.
+    let class_name = node
+        .parent()
+        .and_then(|body| body.parent())
+        .filter(|_| is_constructor && node.kind() == "method_definition")
+        .and_then(|class| class.child_by_field_name("name"));
+    let name = match (&class_name, &name_node) {
+        (Some(name), _) | (None, Some(name)) => source.text(name),
+        (None, None) if node.kind() == "program" => source
+            .uri
+            .path_segments()
+            .and_then(|mut segments| segments.next_back().map(String::from))
+            .unwrap_or_else(|| source.uri.to_string()),
+        (None, None) => String::from("<anonymous>"),
+    };
+    let kind = match &*node.kind() {
+        "program" => lsp::SymbolKind::FILE,
+        "class_declaration" | "class" => lsp::SymbolKind::CLASS,
+        "method_definition" if is_constructor => lsp::SymbolKind::CONSTRUCTOR,
+        "method_definition" => lsp::SymbolKind::METHOD,
+        _ => lsp::SymbolKind::FUNCTION,
+    };
+    let detail = if declaration::is_function(node) {
+        Some(declaration::function_signature(&source.content, node))
+    } else {
+        None
+    };
+    let range = source.range(node);
+    let selection_range = name_node.map_or(lsp::Range::new(range.start, range.start), |name| source.range(&name));
+    lsp::CallHierarchyItem {
+        name,
+        kind,
+        tags: None,
+        detail,
+        uri: source.uri.clone(),
+        range,
+        selection_range,
+        data: Some(serde_json::json!({
+            "offset": node.start_byte(),
+            "kind": node.kind().to_string(),
+        })),
+    }
+}
+
+/// Build the item for a call whose callee could not be resolved.
+fn unresolved(source: &Source, call: &tree_sitter::Node, callee: &tree_sitter::Node) -> lsp::CallHierarchyItem {
+    lsp::CallHierarchyItem {
+        name: declaration::single_line(&source.text(callee)),
+        kind: lsp::SymbolKind::FUNCTION,
+        tags: None,
+        detail: Some(String::from("unresolved")),
+        uri: source.uri.clone(),
+        range: source.range(call),
+        selection_range: source.range(callee),
+        data: None,
+    }
+}
+
+/// Whether two items denote the same function, or the same unresolved callee.
+fn is_same_item(a: &lsp::CallHierarchyItem, b: &lsp::CallHierarchyItem) -> bool {
+    match (&a.data, &b.data) {
+        (Some(a_data), Some(b_data)) => a.uri == b.uri && a_data == b_data,
+        (None, None) => a.name == b.name,
+        _ => false,
+    }
+}
+
+/// Find the node an item was built from.
+fn find<'tree>(source: &'tree Source, item: &lsp::CallHierarchyItem) -> Option<tree_sitter::Node<'tree>> {
+    let data = item.data.as_ref()?;
+    let offset = data.get("offset")?.as_u64()? as u32;
+    let kind = data.get("kind")?.as_str()?;
+    let node = source.tree.root_node().descendant_for_byte_range(offset, offset)?;
+    // NOTE: the document may have changed since the item was built.
+    std::iter::successors(Some(node), |node| node.parent())
+        .find(|node| node.start_byte() == offset && node.kind() == kind)
+}
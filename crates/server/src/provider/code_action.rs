@@ -0,0 +1,195 @@
+use crate::provider::refactor::{self, Refactor};
+use lsp_text::RopeExt;
+use std::collections::HashMap;
+
+pub struct Options {
+    /// Whether the client can resolve the edit of a code action lazily.
+    pub resolve_edit: bool,
+}
+
+impl Options {
+    pub fn new(capabilities: Option<&lsp::ClientCapabilities>) -> Self {
+        let resolve_edit = capabilities
+            .and_then(|capabilities| capabilities.text_document.as_ref())
+            .and_then(|capabilities| capabilities.code_action.as_ref())
+            .and_then(|capabilities| capabilities.resolve_support.as_ref())
+            .map_or(false, |support| support.properties.iter().any(|property| property == "edit"));
+        Options { resolve_edit }
+    }
+}
+
+pub fn code_actions(
+    uri: &lsp::Url,
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    range: lsp::Range,
+    context: &lsp::CodeActionContext,
+    options: Options,
+) -> anyhow::Result<Vec<lsp::CodeActionOrCommand>> {
+    let mut actions = vec![];
+
+    let fixes = missing_fixes(content, tree, context);
+    for fix in fixes.iter().filter(|fix| intersects(&fix.diagnostic.range, &range)) {
+        actions.push(lsp::CodeAction {
+            title: format!("Insert missing `{}`", fix.edit.new_text),
+            kind: Some(lsp::CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![fix.diagnostic.clone()]),
+            edit: Some(workspace_edit(uri, vec![fix.edit.clone()])),
+            is_preferred: Some(true),
+            ..Default::default()
+        });
+    }
+    if !fixes.is_empty() {
+        actions.push(lsp::CodeAction {
+            title: String::from("Insert all missing tokens"),
+            kind: Some(lsp::CodeActionKind::SOURCE_FIX_ALL),
+            diagnostics: Some(fixes.iter().map(|fix| fix.diagnostic.clone()).collect()),
+            edit: Some(workspace_edit(uri, fixes.into_iter().map(|fix| fix.edit).collect())),
+            ..Default::default()
+        });
+    }
+
+    let refactors = Refactor::ALL
+        .iter()
+        .filter(|refactor| is_requested(context, Some(&refactor.kind())))
+        .collect::<Vec<_>>();
+    if !refactors.is_empty() {
+        let refactor_context = refactor::Context::new(content, tree, range)?;
+        for refactor in refactors.into_iter().filter(|refactor| refactor.applies(&refactor_context)) {
+            // NOTE: computing the edits of every applicable refactoring is wasted work when the client
+            // can ask for the edit of the chosen one.
+            let (edit, data) = if options.resolve_edit {
+                let data = serde_json::json!({
+                    "uri": uri,
+                    "refactor": refactor.id(),
+                    "range": range,
+                });
+                (None, Some(data))
+            } else {
+                let edits = refactor.edits(&refactor_context).unwrap_or_default();
+                (Some(workspace_edit(uri, edits)), None)
+            };
+            actions.push(lsp::CodeAction {
+                title: String::from(refactor.title()),
+                kind: Some(refactor.kind()),
+                edit,
+                data,
+                ..Default::default()
+            });
+        }
+    }
+
+    let actions = actions
+        .into_iter()
+        .filter(|action| is_requested(context, action.kind.as_ref()))
+        .map(lsp::CodeActionOrCommand::CodeAction)
+        .collect();
+    Ok(actions)
+}
+
+/// Whether the client asked for actions of the kind, e.g., `source.fixAll` matches a request for
+/// `source`.
+fn is_requested(context: &lsp::CodeActionContext, kind: Option<&lsp::CodeActionKind>) -> bool {
+    match (&context.only, kind) {
+        (Some(only), Some(kind)) => only.iter().any(|only| {
+            let (only, kind) = (only.as_str(), kind.as_str());
+            kind == only || kind.starts_with(&format!("{}.", only))
+        }),
+        (Some(_), None) => false,
+        (None, _) => true,
+    }
+}
+
+/// Return the document URI stored in the data of a refactoring code action.
+pub fn data_uri(action: &lsp::CodeAction) -> Option<lsp::Url> {
+    let uri = action.data.as_ref()?.get("uri")?.as_str()?;
+    lsp::Url::parse(uri).ok()
+}
+
+/// Fill in the edit of a refactoring code action.
+pub fn resolve(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    mut action: lsp::CodeAction,
+) -> anyhow::Result<lsp::CodeAction> {
+    let data = match action.data.as_ref() {
+        Some(data) => data,
+        None => return Ok(action),
+    };
+    let uri = data_uri(&action).ok_or_else(|| anyhow::anyhow!("code action data is missing the uri"))?;
+    let refactor = data
+        .get("refactor")
+        .and_then(|id| id.as_str())
+        .and_then(Refactor::from_id)
+        .ok_or_else(|| anyhow::anyhow!("code action data has an unknown refactoring"))?;
+    let range = serde_json::from_value::<lsp::Range>(data.get("range").cloned().unwrap_or_default())?;
+
+    let context = refactor::Context::new(content, tree, range)?;
+    // NOTE: the document may have changed since the action was offered, in which case there is
+    // nothing left to do.
+    if let Some(edits) = refactor.edits(&context) {
+        action.edit = Some(workspace_edit(&uri, edits));
+    }
+    Ok(action)
+}
+
+fn intersects(a: &lsp::Range, b: &lsp::Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+fn workspace_edit(uri: &lsp::Url, edits: Vec<lsp::TextEdit>) -> lsp::WorkspaceEdit {
+    lsp::WorkspaceEdit {
+        changes: Some(HashMap::from([(uri.clone(), edits)])),
+        ..Default::default()
+    }
+}
+
+/// An edit inserting a `MISSING` token, together with the diagnostic reporting it.
+struct MissingFix {
+    diagnostic: lsp::Diagnostic,
+    edit: lsp::TextEdit,
+}
+
+/// Compute a fix for every `MISSING` token in the tree, linked to the matching diagnostic sent by
+/// the client if there is one.
+fn missing_fixes(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    context: &lsp::CodeActionContext,
+) -> Vec<MissingFix> {
+    let mut fixes = vec![];
+
+    let mut cursor = tree.walk();
+    'nodes: loop {
+        let node = cursor.node();
+
+        // NOTE: only anonymous tokens, e.g., `)`, can be inserted; a missing identifier has no text.
+        if node.is_missing() && !node.is_named() {
+            let diagnostic = crate::provider::diagnostics::missing(content, &node);
+            let diagnostic = context
+                .diagnostics
+                .iter()
+                .find(|candidate| candidate.range == diagnostic.range && candidate.message == diagnostic.message)
+                .cloned()
+                .unwrap_or(diagnostic);
+            let position = content.tree_sitter_range_to_lsp_range(node.range()).start;
+            let edit = lsp::TextEdit::new(lsp::Range::new(position, position), node.kind().to_string());
+            fixes.push(MissingFix { diagnostic, edit });
+        }
+
+        if node.has_error() && cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if cursor.goto_next_sibling() {
+                continue 'nodes;
+            }
+            if !cursor.goto_parent() {
+                break 'nodes;
+            }
+        }
+    }
+
+    fixes
+}
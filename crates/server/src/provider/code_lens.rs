@@ -0,0 +1,127 @@
+use crate::core::scope::{Binding, BindingKind, Scopes};
+use std::collections::HashMap;
+
+/// The command run when a code lens is clicked, which opens the references in the editor.
+pub static SHOW_REFERENCES: &str = "editor.action.showReferences";
+
+/// The references to the module bindings of a document, by the offset of their identifier.
+#[derive(Default)]
+pub struct Index {
+    references: HashMap<u32, Vec<lsp::Range>>,
+    /// The name and number of references of each module binding, in order.
+    counts: Vec<(String, usize)>,
+}
+
+impl Index {
+    pub fn new(content: &ropey::Rope, tree: &tree_sitter::Tree) -> Self {
+        let scopes = Scopes::analyze(content, tree);
+        let bindings = scopes.bindings.iter().filter(|binding| binding.scope == 0);
+        let references = bindings
+            .clone()
+            .map(|binding| {
+                let ranges = binding
+                    .references
+                    .iter()
+                    .map(|&reference| scopes.references[reference].range)
+                    .collect();
+                (binding.span.start, ranges)
+            })
+            .collect();
+        let counts = bindings
+            .map(|binding| (binding.name.clone(), binding.references.len()))
+            .collect();
+        Index { references, counts }
+    }
+
+    /// Whether the lenses resolved from the index would differ from those resolved from the other
+    /// index, i.e., whether the module bindings or their numbers of references differ.
+    pub fn changes_lenses(&self, other: &Index) -> bool {
+        self.counts != other.counts
+    }
+
+    /// Return the references to the binding whose identifier starts at the offset.
+    pub fn references(&self, offset: u32) -> Option<&[lsp::Range]> {
+        self.references.get(&offset).map(Vec::as_slice)
+    }
+}
+
+/// Compute an unresolved code lens for every top-level function and class and every exported
+/// binding.
+pub fn code_lenses(uri: &lsp::Url, content: &ropey::Rope, tree: &tree_sitter::Tree) -> Vec<lsp::CodeLens> {
+    let scopes = Scopes::analyze(content, tree);
+    let exports = exports(tree);
+    scopes
+        .bindings
+        .iter()
+        .filter(|binding| binding.scope == 0)
+        .filter(|binding| {
+            matches!(binding.kind, BindingKind::Function | BindingKind::Class)
+                || exports.iter().any(|export| export.start <= binding.span.start && binding.span.end <= export.end)
+                || is_exported_by_name(tree, &scopes, binding)
+        })
+        .map(|binding| lsp::CodeLens {
+            range: binding.range,
+            command: None,
+            data: Some(serde_json::json!({
+                "uri": uri,
+                "offset": binding.span.start,
+            })),
+        })
+        .collect()
+}
+
+/// Return the document URI and binding offset stored in the data of a code lens.
+pub fn data(lens: &lsp::CodeLens) -> Option<(lsp::Url, u32)> {
+    let data = lens.data.as_ref()?;
+    let uri = lsp::Url::parse(data.get("uri")?.as_str()?).ok()?;
+    let offset = data.get("offset")?.as_u64()?;
+    Some((uri, offset as u32))
+}
+
+/// Fill in the command of a code lens with the number of references to its binding.
+pub fn resolve(uri: &lsp::Url, index: &Index, offset: u32, mut lens: lsp::CodeLens) -> lsp::CodeLens {
+    // NOTE: the binding may be gone if the document changed since the lens was computed, in which
+    // case the lens is about to be refreshed anyway.
+    let references = index.references(offset).unwrap_or_default();
+    let title = match references.len() {
+        1 => String::from("1 reference"),
+        n => format!("{} references", n),
+    };
+    let locations = references
+        .iter()
+        .map(|&range| lsp::Location::new(uri.clone(), range))
+        .collect::<Vec<_>>();
+    let arguments = vec![
+        serde_json::json!(uri),
+        serde_json::json!(lens.range.start),
+        serde_json::json!(locations),
+    ];
+    lens.command = Some(lsp::Command {
+        title,
+        command: String::from(SHOW_REFERENCES),
+        arguments: Some(arguments),
+    });
+    lens
+}
+
+/// The byte ranges of the declarations in top-level `export` statements.
+fn exports(tree: &tree_sitter::Tree) -> Vec<std::ops::Range<u32>> {
+    let root = tree.root_node();
+    (0 .. root.named_child_count())
+        .filter_map(|i| root.named_child(i))
+        .filter(|child| child.kind() == "export_statement")
+        .filter_map(|export| export.child_by_field_name("declaration"))
+        .map(|declaration| declaration.start_byte() .. declaration.end_byte())
+        .collect()
+}
+
+/// Whether the binding is exported by an `export { x }` clause.
+fn is_exported_by_name(tree: &tree_sitter::Tree, scopes: &Scopes, binding: &Binding) -> bool {
+    let root = tree.root_node();
+    binding.references.iter().any(|&reference| {
+        let span = &scopes.references[reference].span;
+        root.named_descendant_for_byte_range(span.start, span.end)
+            .and_then(|node| node.parent())
+            .map_or(false, |parent| parent.kind() == "export_specifier")
+    })
+}
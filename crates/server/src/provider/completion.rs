@@ -0,0 +1,406 @@
+use crate::core::{
+    declaration,
+    scope::{BindingKind, Occurrence, Scopes},
+};
+use lsp_text::RopeExt;
+
+/// Keywords which begin a statement.
+static STATEMENT_KEYWORDS: &[&str] = &[
+    "class", "const", "debugger", "do", "for", "function", "if", "let", "switch", "throw", "try", "var", "while",
+];
+
+/// Keywords which begin (or are) an expression.
+static EXPRESSION_KEYWORDS: &[&str] = &[
+    "async", "class", "delete", "false", "function", "new", "null", "this", "true", "typeof", "void",
+];
+
+/// Keywords which begin a class member.
+static CLASS_MEMBER_KEYWORDS: &[&str] = &["async", "get", "set", "static"];
+
+/// Snippets offered in statement position as triples of label, description and body.
+static SNIPPETS: &[(&str, &str, &str)] = &[
+    ("class", "class declaration", "class ${1:Name} {\n\tconstructor(${2}) {\n\t\t$0\n\t}\n}"),
+    ("for", "for loop", "for (let ${1:i} = 0; ${1:i} < ${2:length}; ${1:i}++) {\n\t$0\n}"),
+    ("forin", "for-in loop", "for (const ${1:key} in ${2:object}) {\n\t$0\n}"),
+    ("forof", "for-of loop", "for (const ${1:item} of ${2:items}) {\n\t$0\n}"),
+    ("function", "function declaration", "function ${1:name}(${2}) {\n\t$0\n}"),
+    ("if", "if statement", "if (${1:condition}) {\n\t$0\n}"),
+    ("ifelse", "if-else statement", "if (${1:condition}) {\n\t$2\n} else {\n\t$0\n}"),
+    ("switch", "switch statement", "switch (${1:key}) {\n\tcase ${2:value}:\n\t\t$0\n\t\tbreak;\n}"),
+    ("trycatch", "try-catch statement", "try {\n\t$0\n} catch (${1:error}) {\n\t\n}"),
+    ("while", "while loop", "while (${1:condition}) {\n\t$0\n}"),
+];
+
+/// Client settings which affect the completion items the server reports.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    pub snippets: bool,
+    pub markdown: bool,
+}
+
+impl Options {
+    pub fn new(capabilities: Option<&lsp::ClientCapabilities>) -> Self {
+        let capabilities = capabilities
+            .and_then(|capabilities| capabilities.text_document.as_ref())
+            .and_then(|capabilities| capabilities.completion.as_ref())
+            .and_then(|capabilities| capabilities.completion_item.as_ref());
+        let snippets = capabilities
+            .and_then(|capabilities| capabilities.snippet_support)
+            .unwrap_or_default();
+        let markdown = capabilities
+            .and_then(|capabilities| capabilities.documentation_format.as_ref())
+            .map_or(false, |formats| formats.first() == Some(&lsp::MarkupKind::Markdown));
+        Options { snippets, markdown }
+    }
+}
+
+/// The syntactic position of the cursor, which determines the keywords that are valid there.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Context {
+    Statement,
+    Expression,
+    ClassMember,
+    SwitchBody,
+}
+
+/// Facts about the code enclosing the cursor which further restrict the valid keywords.
+#[derive(Default)]
+struct Enclosing {
+    function: bool,
+    is_async: bool,
+    generator: bool,
+    method: bool,
+    breakable: bool,
+    continuable: bool,
+    module: bool,
+}
+
+pub fn completion(
+    uri: &lsp::Url,
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+    options: Options,
+) -> anyhow::Result<Option<lsp::CompletionResponse>> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+    let start = prefix_start(content, byte);
+
+    let node = match tree.root_node().named_descendant_for_byte_range(start, byte) {
+        Some(node) => node,
+        None => return Ok(None),
+    };
+    let context = match context(content, &node, start, byte) {
+        Some(context) => context,
+        None => return Ok(None),
+    };
+
+    let scopes = Scopes::analyze(content, tree);
+    // NOTE: the user is naming a new binding.
+    if let Some(Occurrence::Binding(_)) = scopes.occurrence_at(byte) {
+        return Ok(None);
+    }
+
+    let mut items = vec![];
+
+    if context != Context::ClassMember && context != Context::SwitchBody {
+        let scope = scopes.scope_at(byte);
+        for binding in scopes.visible_bindings(scope) {
+            let binding = &scopes.bindings[binding];
+            let data = serde_json::json!({ "uri": uri, "offset": binding.span.start });
+            items.push(lsp::CompletionItem {
+                label: binding.name.clone(),
+                kind: Some(binding_kind(binding.kind)),
+                sort_text: Some(format!("0{}", binding.name)),
+                data: Some(data),
+                ..Default::default()
+            });
+        }
+    }
+
+    for keyword in keywords(content, context, &enclosing(&node), &node, start) {
+        items.push(lsp::CompletionItem {
+            label: keyword.to_string(),
+            kind: Some(lsp::CompletionItemKind::KEYWORD),
+            sort_text: Some(format!("1{}", keyword)),
+            ..Default::default()
+        });
+    }
+
+    if options.snippets && context == Context::Statement {
+        for (label, description, body) in SNIPPETS {
+            items.push(lsp::CompletionItem {
+                label: label.to_string(),
+                kind: Some(lsp::CompletionItemKind::SNIPPET),
+                detail: Some(description.to_string()),
+                insert_text: Some(body.to_string()),
+                insert_text_format: Some(lsp::InsertTextFormat::SNIPPET),
+                sort_text: Some(format!("2{}", label)),
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(Some(lsp::CompletionResponse::Array(items)))
+}
+
+/// Return the document which the completion item refers to, if it needs resolving.
+pub fn data_uri(item: &lsp::CompletionItem) -> Option<lsp::Url> {
+    let uri = item.data.as_ref()?.get("uri")?.as_str()?;
+    lsp::Url::parse(uri).ok()
+}
+
+/// Fill in the detail and documentation of a binding completion item.
+pub fn resolve(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    mut item: lsp::CompletionItem,
+    options: Options,
+) -> lsp::CompletionItem {
+    let offset = item
+        .data
+        .as_ref()
+        .and_then(|data| data.get("offset"))
+        .and_then(serde_json::Value::as_u64);
+    let scopes = Scopes::analyze(content, tree);
+    let binding = scopes
+        .bindings
+        .iter()
+        .find(|binding| Some(u64::from(binding.span.start)) == offset && binding.name == item.label);
+    let binding = match binding {
+        Some(binding) => binding,
+        None => return item,
+    };
+
+    item.detail = Some(declaration::signature(content, tree, binding));
+    let documentation = declaration::node(tree, binding).and_then(|node| declaration::documentation(content, &node));
+    item.documentation = documentation.map(|documentation| {
+        let (kind, value) = if options.markdown {
+            (lsp::MarkupKind::Markdown, documentation.to_markdown())
+        } else {
+            (lsp::MarkupKind::PlainText, documentation.to_plaintext())
+        };
+        lsp::Documentation::MarkupContent(lsp::MarkupContent { kind, value })
+    });
+    item
+}
+
+fn binding_kind(kind: BindingKind) -> lsp::CompletionItemKind {
+    match kind {
+        BindingKind::Function => lsp::CompletionItemKind::FUNCTION,
+        BindingKind::Class => lsp::CompletionItemKind::CLASS,
+        BindingKind::Const => lsp::CompletionItemKind::CONSTANT,
+        BindingKind::Import => lsp::CompletionItemKind::MODULE,
+        BindingKind::Var | BindingKind::Let | BindingKind::Parameter | BindingKind::CatchParameter => {
+            lsp::CompletionItemKind::VARIABLE
+        },
+    }
+}
+
+/// Return the start of the identifier being typed before the cursor.
+fn prefix_start(content: &ropey::Rope, byte: u32) -> u32 {
+    let mut start = byte as usize;
+    let mut chars = content.chars_at(content.byte_to_char(start));
+    while let Some(c) = chars.prev() {
+        if !(c == '$' || c == '_' || c.is_alphanumeric()) {
+            break;
+        }
+        start -= c.len_utf8();
+    }
+    start as u32
+}
+
+/// Classify the position of the cursor. Returns [`None`] where no completions apply, e.g., inside
+/// comments and strings or after a `.`.
+fn context(content: &ropey::Rope, node: &tree_sitter::Node, start: u32, byte: u32) -> Option<Context> {
+    let kind = node.kind();
+    let inside = |node: &tree_sitter::Node| node.start_byte() < byte && byte < node.end_byte();
+    match &*kind {
+        "comment" if inside(node) => return None,
+        // NOTE: a line comment continues up to the end of its line.
+        "comment" if byte == node.end_byte() && content.utf8_text_for_tree_sitter_node(node).starts_with("//") => {
+            return None;
+        },
+        "string" | "string_fragment" | "escape_sequence" | "template_string" | "regex" | "regex_pattern"
+            if inside(node) =>
+        {
+            return None;
+        },
+        "formal_parameters" | "object_pattern" | "array_pattern" | "number" => return None,
+        _ => {},
+    }
+
+    // NOTE: nothing is known about the properties of values.
+    let before = content
+        .chars_at(content.byte_to_char(start as usize))
+        .prev()
+        .filter(|c| !c.is_whitespace());
+    if before == Some('.') {
+        return None;
+    }
+
+    let container = if kind == "identifier" || kind == "property_identifier" {
+        let parent = node.parent()?;
+        if parent.kind() == "member_expression" && parent.child_by_field_name("property").as_ref() == Some(node) {
+            return None;
+        }
+        // NOTE: an identifier alone in a statement is a statement keyword being typed.
+        let is_statement = parent.kind() == "expression_statement" && parent.start_byte() == node.start_byte();
+        if is_statement {
+            parent.parent()?
+        } else if matches!(&*parent.kind(), "class_body" | "public_field_definition" | "field_definition") {
+            return Some(Context::ClassMember);
+        } else if matches!(&*parent.kind(), "program" | "statement_block" | "ERROR") {
+            parent
+        } else {
+            return Some(Context::Expression);
+        }
+    } else {
+        node.clone()
+    };
+
+    let context = match &*container.kind() {
+        "program" | "statement_block" | "switch_case" | "switch_default" | "ERROR" => Context::Statement,
+        "class_body" => Context::ClassMember,
+        "switch_body" => Context::SwitchBody,
+        _ => Context::Expression,
+    };
+    Some(context)
+}
+
+fn enclosing(node: &tree_sitter::Node) -> Enclosing {
+    let has_child = |node: &tree_sitter::Node, kind: &str| {
+        (0 .. node.child_count())
+            .filter_map(|i| node.child(i))
+            .any(|child| child.kind() == kind)
+    };
+
+    let mut enclosing = Enclosing::default();
+    // NOTE: `break` and `continue` cannot cross function boundaries and `super` cannot cross those
+    // of non-arrow functions.
+    let mut crossed_function = false;
+    let mut crossed_non_arrow_function = false;
+    let mut crossed_block = false;
+    for ancestor in std::iter::successors(Some(node.clone()), |node| node.parent()) {
+        let kind = ancestor.kind();
+        match &*kind {
+            "for_statement" | "for_in_statement" | "while_statement" | "do_statement" if !crossed_function => {
+                enclosing.breakable = true;
+                enclosing.continuable = true;
+            },
+            "switch_statement" if !crossed_function => enclosing.breakable = true,
+            "statement_block" | "class_body" | "switch_body" => crossed_block = true,
+            // NOTE: `import` and `export` are only valid at the top level.
+            "program" => enclosing.module = !crossed_block,
+            _ => {},
+        }
+        if declaration::is_function(&ancestor) {
+            if kind == "method_definition" && !crossed_non_arrow_function {
+                enclosing.method = true;
+            }
+            if !crossed_function {
+                enclosing.function = true;
+                enclosing.is_async = has_child(&ancestor, "async");
+                enclosing.generator = kind.starts_with("generator") || has_child(&ancestor, "*");
+            }
+            crossed_function = true;
+            crossed_non_arrow_function |= kind != "arrow_function";
+        }
+    }
+    enclosing
+}
+
+fn keywords(
+    content: &ropey::Rope,
+    context: Context,
+    enclosing: &Enclosing,
+    node: &tree_sitter::Node,
+    start: u32,
+) -> Vec<&'static str> {
+    let mut keywords = vec![];
+    let expression = |keywords: &mut Vec<&'static str>| {
+        keywords.extend(EXPRESSION_KEYWORDS);
+        // NOTE: modules allow `await` at the top level.
+        if enclosing.is_async || !enclosing.function {
+            keywords.push("await");
+        }
+        if enclosing.generator {
+            keywords.push("yield");
+        }
+        if enclosing.method {
+            keywords.push("super");
+        }
+    };
+
+    match context {
+        Context::Statement => {
+            keywords.extend(STATEMENT_KEYWORDS);
+            expression(&mut keywords);
+            if enclosing.function {
+                keywords.push("return");
+            }
+            if enclosing.breakable {
+                keywords.push("break");
+            }
+            if enclosing.continuable {
+                keywords.push("continue");
+            }
+            if enclosing.module {
+                keywords.extend(["export", "import"]);
+            }
+            keywords.extend(follow_up_keywords(node, start));
+        },
+        Context::Expression => expression(&mut keywords),
+        Context::ClassMember => {
+            keywords.extend(CLASS_MEMBER_KEYWORDS);
+            let class_body = std::iter::successors(Some(node.clone()), |node| node.parent())
+                .find(|node| node.kind() == "class_body");
+            let has_constructor = class_body.map_or(false, |class_body| {
+                (0 .. class_body.named_child_count())
+                    .filter_map(|i| class_body.named_child(i))
+                    .filter_map(|member| member.child_by_field_name("name"))
+                    .any(|name| content.utf8_text_for_tree_sitter_node(&name) == "constructor")
+            });
+            if !has_constructor {
+                keywords.push("constructor");
+            }
+        },
+        Context::SwitchBody => keywords.extend(["case", "default"]),
+    }
+
+    keywords.sort_unstable();
+    keywords.dedup();
+    keywords
+}
+
+/// Keywords which continue the statement preceding the cursor, e.g., `else` after an `if`.
+fn follow_up_keywords(node: &tree_sitter::Node, start: u32) -> Vec<&'static str> {
+    let container = std::iter::successors(Some(node.clone()), |node| node.parent())
+        .find(|node| matches!(&*node.kind(), "program" | "statement_block" | "switch_case" | "switch_default"));
+    let previous = container.and_then(|container| {
+        (0 .. container.named_child_count())
+            .filter_map(|i| container.named_child(i))
+            .filter(|child| child.end_byte() <= start && child.kind() != "comment")
+            .last()
+    });
+
+    match previous {
+        Some(previous) if previous.kind() == "if_statement" => {
+            if previous.child_by_field_name("alternative").is_none() {
+                vec!["else"]
+            } else {
+                vec![]
+            }
+        },
+        Some(previous) if previous.kind() == "try_statement" => {
+            let mut keywords = vec![];
+            if previous.child_by_field_name("handler").is_none() {
+                keywords.push("catch");
+            }
+            if previous.child_by_field_name("finalizer").is_none() {
+                keywords.push("finally");
+            }
+            keywords
+        },
+        _ => vec![],
+    }
+}
@@ -0,0 +1,39 @@
+use crate::core::scope::Scopes;
+use lsp_text::RopeExt;
+
+/// Return the location of the binding for the identifier at the given position.
+pub fn definition(
+    uri: &lsp::Url,
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+) -> anyhow::Result<Option<lsp::Location>> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+    let scopes = Scopes::analyze(content, tree);
+    let location = scopes.binding_at(byte).map(|binding| {
+        let range = scopes.bindings[binding].range;
+        lsp::Location::new(uri.clone(), range)
+    });
+    Ok(location)
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::definition;
+    use crate::core::test::{parse, position};
+
+    #[test]
+    fn finds_the_binding() {
+        let uri = lsp::Url::parse("file:///a.js").unwrap();
+        let (content, tree) = parse("let a = 1;\nfunction f(a) {\n  return a;\n}\na + 1;\n");
+
+        let location = definition(&uri, &content, &tree, position(&content, "a;\n}")).unwrap().unwrap();
+        assert_eq!(location.range.start, position(&content, "a) {"));
+
+        let location = definition(&uri, &content, &tree, position(&content, "a + 1")).unwrap();
+        assert_eq!(location.unwrap().range.start, position(&content, "a = 1"));
+
+        assert_eq!(definition(&uri, &content, &tree, position(&content, "f(a)")).unwrap().unwrap().uri, uri);
+        assert_eq!(definition(&uri, &content, &tree, position(&content, "= 1")).unwrap(), None);
+    }
+}
@@ -0,0 +1,94 @@
+use lsp_text::RopeExt;
+
+/// Compute a diagnostic for every `ERROR` and `MISSING` node in the [`tree_sitter::Tree`].
+pub fn syntax(content: &ropey::Rope, tree: &tree_sitter::Tree) -> Vec<lsp::Diagnostic> {
+    let mut diagnostics = vec![];
+
+    let mut cursor = tree.walk();
+    'nodes: loop {
+        let node = cursor.node();
+
+        // NOTE: `has_error` is also true for the ancestors of an error so we only need to descend
+        // into subtrees where it holds.
+        let mut descend = node.has_error();
+
+        if node.is_missing() {
+            diagnostics.push(missing(content, &node));
+            descend = false;
+        } else if node.is_error() {
+            diagnostics.push(make_diagnostic(content, &node, error_message(content, &node)));
+            // NOTE: nested errors are just noise, but we still want to report missing nodes.
+            collect_missing(content, &node, &mut diagnostics);
+            descend = false;
+        }
+
+        if descend && cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if cursor.goto_next_sibling() {
+                continue 'nodes;
+            }
+            if !cursor.goto_parent() {
+                break 'nodes;
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn collect_missing(content: &ropey::Rope, node: &tree_sitter::Node, diagnostics: &mut Vec<lsp::Diagnostic>) {
+    for i in 0 .. node.child_count() {
+        if let Some(child) = node.child(i) {
+            if child.is_missing() {
+                diagnostics.push(missing(content, &child));
+            } else if child.has_error() {
+                collect_missing(content, &child, diagnostics);
+            }
+        }
+    }
+}
+
+/// Compute the diagnostic for a `MISSING` node.
+pub fn missing(content: &ropey::Rope, node: &tree_sitter::Node) -> lsp::Diagnostic {
+    make_diagnostic(content, node, missing_message(node))
+}
+
+fn make_diagnostic(content: &ropey::Rope, node: &tree_sitter::Node, message: String) -> lsp::Diagnostic {
+    let range = content.tree_sitter_range_to_lsp_range(node.range());
+    let severity = Some(lsp::DiagnosticSeverity::ERROR);
+    let code = Some(lsp::NumberOrString::String(String::from("syntax-error")));
+    let source = Some(String::from(SOURCE));
+    lsp::Diagnostic {
+        range,
+        severity,
+        code,
+        source,
+        message,
+        ..Default::default()
+    }
+}
+
+fn missing_message(node: &tree_sitter::Node) -> String {
+    let kind = node.kind();
+    if node.is_named() {
+        format!("missing {}", kind.replace('_', " "))
+    } else {
+        format!("missing `{}`", kind)
+    }
+}
+
+fn error_message(content: &ropey::Rope, node: &tree_sitter::Node) -> String {
+    let text = content.utf8_text_for_tree_sitter_node(node);
+    let text = text.trim();
+    if text.is_empty() || text.contains('\n') || text.chars().count() > 32 {
+        String::from("unexpected tokens")
+    } else {
+        format!("unexpected token `{}`", text)
+    }
+}
+
+/// The value of [`lsp::Diagnostic::source`] for diagnostics computed by the server.
+pub static SOURCE: &str = "demo-lsp";
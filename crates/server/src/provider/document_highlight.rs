@@ -0,0 +1,51 @@
+use crate::core::scope::Scopes;
+use lsp_text::RopeExt;
+
+/// Return a highlight for every occurrence of the name at the given position, distinguishing
+/// reads from writes.
+pub fn document_highlight(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+) -> anyhow::Result<Option<Vec<lsp::DocumentHighlight>>> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+    let scopes = Scopes::analyze(content, tree);
+    let occurrence = match scopes.occurrence_at(byte) {
+        Some(occurrence) => occurrence,
+        None => return Ok(None),
+    };
+    let highlights = scopes
+        .related(occurrence)
+        .into_iter()
+        .map(|occurrence| {
+            let range = scopes.range_of(occurrence);
+            let kind = if scopes.is_write(occurrence) {
+                Some(lsp::DocumentHighlightKind::WRITE)
+            } else {
+                Some(lsp::DocumentHighlightKind::READ)
+            };
+            lsp::DocumentHighlight { range, kind }
+        })
+        .collect();
+    Ok(Some(highlights))
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::document_highlight;
+    use crate::core::test::{parse, position};
+
+    #[test]
+    fn distinguishes_reads_and_writes() {
+        let (content, tree) = parse("let a = 1;\na = 2;\na += 3;\na++;\n[a] = [4];\nf(a);\n");
+        let kinds: Vec<_> = document_highlight(&content, &tree, position(&content, "a);"))
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|highlight| (highlight.range.start.line, highlight.kind.unwrap()))
+            .collect();
+        let write = lsp::DocumentHighlightKind::WRITE;
+        let read = lsp::DocumentHighlightKind::READ;
+        assert_eq!(kinds, [(0, write), (1, write), (2, write), (3, write), (4, write), (5, read)]);
+    }
+}
@@ -0,0 +1,276 @@
+use lsp_text::RopeExt;
+
+/// Query matching the declarations which are reported as symbols.
+///
+/// Every pattern captures the declared name as `@identifier` and the declaration itself under a
+/// name describing how it was declared.
+///
+/// NOTE: the grammar loaded by the web backend (0.19) names class fields `public_field_definition`
+/// while the native one (0.20) names them `field_definition`, and queries are rejected if they
+/// mention a node kind which the grammar does not have.
+#[cfg(feature = "wasm")]
+pub static QUERY: &str = indoc::indoc! {r"
+  (function_declaration
+    name: (identifier) @identifier) @function_declaration
+  (generator_function_declaration
+    name: (identifier) @identifier) @function_declaration
+  (lexical_declaration
+    (variable_declarator
+      name: (identifier) @identifier) @lexical_declaration)
+  (variable_declaration
+    (variable_declarator
+      name: (identifier) @identifier) @variable_declaration)
+  (class_declaration
+    name: (identifier) @identifier) @class_declaration
+  (method_definition
+    name: (_) @identifier) @method_definition
+  (public_field_definition
+    property: (_) @identifier) @field_definition
+  (pair
+    key: (_) @identifier) @pair
+"};
+
+/// Query matching the declarations which are reported as symbols; see the web version above.
+#[cfg(feature = "native")]
+pub static QUERY: &str = indoc::indoc! {r"
+  (function_declaration
+    name: (identifier) @identifier) @function_declaration
+  (generator_function_declaration
+    name: (identifier) @identifier) @function_declaration
+  (lexical_declaration
+    (variable_declarator
+      name: (identifier) @identifier) @lexical_declaration)
+  (variable_declaration
+    (variable_declarator
+      name: (identifier) @identifier) @variable_declaration)
+  (class_declaration
+    name: (identifier) @identifier) @class_declaration
+  (method_definition
+    name: (_) @identifier) @method_definition
+  (field_definition
+    property: (_) @identifier) @field_definition
+  (pair
+    key: (_) @identifier) @pair
+"};
+
+/// A declaration matched by [`QUERY`].
+pub struct Declaration<'tree> {
+    /// The name of the capture for the declaration node.
+    pub capture: String,
+    pub node: tree_sitter::Node<'tree>,
+    pub identifier: tree_sitter::Node<'tree>,
+}
+
+/// Match the declarations of a document against [`QUERY`].
+pub fn declarations<'tree>(
+    query: &crate::core::query::Query,
+    content: &ropey::Rope,
+    tree: &'tree tree_sitter::Tree,
+) -> Vec<Declaration<'tree>> {
+    let mut declarations = vec![];
+    for r#match in crate::core::query::QueryCursor::new().matches(query, &tree.root_node(), content) {
+        let mut declaration = None;
+        let mut identifier = None;
+        for capture in r#match.captures {
+            let name = &query.capture_names()[capture.index as usize];
+            if name == "identifier" {
+                identifier = Some(capture.node);
+            } else {
+                declaration = Some((name.clone(), capture.node));
+            }
+        }
+        if let (Some((capture, node)), Some(identifier)) = (declaration, identifier) {
+            declarations.push(Declaration {
+                capture,
+                node,
+                identifier,
+            });
+        }
+    }
+    declarations
+}
+
+/// Build the symbol outline for the given declarations, nesting each symbol under the innermost
+/// symbol whose declaration contains it.
+pub fn nested(content: &ropey::Rope, declarations: Vec<Declaration>) -> Vec<lsp::DocumentSymbol> {
+    fn attach(
+        stack: &mut [(std::ops::Range<u32>, lsp::DocumentSymbol)],
+        roots: &mut Vec<lsp::DocumentSymbol>,
+        symbol: lsp::DocumentSymbol,
+    ) {
+        if let Some((_, parent)) = stack.last_mut() {
+            parent.children.get_or_insert_with(Vec::new).push(symbol);
+        } else {
+            roots.push(symbol);
+        }
+    }
+
+    let mut symbols = declarations
+        .iter()
+        .filter_map(|declaration| {
+            let range = declaration.node.start_byte() .. declaration.node.end_byte();
+            make_symbol(content, declaration).map(|symbol| (range, symbol))
+        })
+        .collect::<Vec<_>>();
+    symbols.sort_by(|(a, _), (b, _)| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+    let mut roots = vec![];
+    let mut stack: Vec<(std::ops::Range<u32>, lsp::DocumentSymbol)> = vec![];
+
+    for (range, symbol) in symbols {
+        while let Some((parent, _)) = stack.last() {
+            if parent.start <= range.start && range.end <= parent.end {
+                break;
+            }
+            if let Some((_, done)) = stack.pop() {
+                attach(&mut stack, &mut roots, done);
+            }
+        }
+        stack.push((range, symbol));
+    }
+
+    while let Some((_, done)) = stack.pop() {
+        attach(&mut stack, &mut roots, done);
+    }
+
+    roots
+}
+
+/// Flatten a symbol outline, recording the name of each parent as the container name.
+pub fn flat(uri: &lsp::Url, symbols: Vec<lsp::DocumentSymbol>) -> Vec<lsp::SymbolInformation> {
+    fn visit(
+        uri: &lsp::Url,
+        container_name: Option<&str>,
+        symbol: lsp::DocumentSymbol,
+        result: &mut Vec<lsp::SymbolInformation>,
+    ) {
+        #[allow(deprecated)]
+        result.push(lsp::SymbolInformation {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            tags: symbol.tags,
+            deprecated: Default::default(),
+            location: lsp::Location::new(uri.clone(), symbol.range),
+            container_name: container_name.map(Into::into),
+        });
+        for child in symbol.children.into_iter().flatten() {
+            visit(uri, Some(&symbol.name), child, result);
+        }
+    }
+
+    let mut result = vec![];
+    for symbol in symbols {
+        visit(uri, None, symbol, &mut result);
+    }
+    result
+}
+
+fn make_symbol(content: &ropey::Rope, declaration: &Declaration) -> Option<lsp::DocumentSymbol> {
+    let node = &declaration.node;
+
+    let (kind, detail) = match declaration.capture.as_str() {
+        "function_declaration" => (lsp::SymbolKind::FUNCTION, parameters_detail(content, node)),
+        "class_declaration" => (lsp::SymbolKind::CLASS, heritage_detail(content, node)),
+        "lexical_declaration" | "variable_declaration" => {
+            let keyword = node
+                .parent()
+                .and_then(|parent| parent.child(0))
+                .map(|keyword| keyword.kind().to_string());
+            match node.child_by_field_name("value") {
+                Some(value) if is_function(&value) => (lsp::SymbolKind::FUNCTION, parameters_detail(content, &value)),
+                Some(value) if value.kind() == "class" => (lsp::SymbolKind::CLASS, heritage_detail(content, &value)),
+                _ if keyword.as_deref() == Some("const") => (lsp::SymbolKind::CONSTANT, keyword),
+                _ => (lsp::SymbolKind::VARIABLE, keyword),
+            }
+        },
+        "method_definition" => {
+            if !is_owned_member(node) {
+                return None;
+            }
+            let name = content.utf8_text_for_tree_sitter_node(&declaration.identifier);
+            if let Some(accessor) = modifier(node, &["get", "set"]) {
+                (lsp::SymbolKind::PROPERTY, Some(accessor))
+            } else if name == "constructor" {
+                (lsp::SymbolKind::CONSTRUCTOR, parameters_detail(content, node))
+            } else {
+                (lsp::SymbolKind::METHOD, parameters_detail(content, node))
+            }
+        },
+        "field_definition" => (lsp::SymbolKind::FIELD, modifier(node, &["static"])),
+        "pair" => {
+            if !is_owned_member(node) {
+                return None;
+            }
+            match node.child_by_field_name("value") {
+                Some(value) if is_function(&value) => (lsp::SymbolKind::METHOD, parameters_detail(content, &value)),
+                _ => (lsp::SymbolKind::PROPERTY, None),
+            }
+        },
+        _ => return None,
+    };
+
+    let name = content.utf8_text_for_tree_sitter_node(&declaration.identifier).into();
+    let range = content.tree_sitter_range_to_lsp_range(node.range());
+    let selection_range = content.tree_sitter_range_to_lsp_range(declaration.identifier.range());
+
+    #[allow(deprecated)]
+    Some(lsp::DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: Default::default(),
+        deprecated: Default::default(),
+        range,
+        selection_range,
+        children: Default::default(),
+    })
+}
+
+/// Whether a class or object member belongs to a declaration which is itself reported as a
+/// symbol. Members of class bodies always do; members of object literals only do when the object
+/// is the value of a variable or of another such member.
+fn is_owned_member(node: &tree_sitter::Node) -> bool {
+    let object = match node.parent() {
+        Some(parent) if parent.kind() == "object" => parent,
+        Some(_) => return true,
+        None => return false,
+    };
+    match object.parent() {
+        Some(owner) if owner.kind() == "variable_declarator" => true,
+        Some(owner) if owner.kind() == "pair" => is_owned_member(&owner),
+        _ => false,
+    }
+}
+
+fn is_function(node: &tree_sitter::Node) -> bool {
+    matches!(&*node.kind(), "arrow_function" | "function" | "generator_function")
+}
+
+/// Return the first of the given keywords which occurs as an anonymous child of the node.
+fn modifier(node: &tree_sitter::Node, keywords: &[&str]) -> Option<String> {
+    (0 .. node.child_count())
+        .filter_map(|i| node.child(i))
+        .filter(|child| !child.is_named())
+        .map(|child| child.kind().to_string())
+        .find(|kind| keywords.contains(&kind.as_str()))
+}
+
+fn parameters_detail(content: &ropey::Rope, node: &tree_sitter::Node) -> Option<String> {
+    let parameters = node
+        .child_by_field_name("parameters")
+        .or_else(|| node.child_by_field_name("parameter"))?;
+    let text = content.utf8_text_for_tree_sitter_node(&parameters);
+    Some(single_line(&text))
+}
+
+fn heritage_detail(content: &ropey::Rope, node: &tree_sitter::Node) -> Option<String> {
+    let heritage = (0 .. node.named_child_count())
+        .filter_map(|i| node.named_child(i))
+        .find(|child| child.kind() == "class_heritage")?;
+    let text = content.utf8_text_for_tree_sitter_node(&heritage);
+    Some(single_line(&text))
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,182 @@
+use lsp_text::RopeExt;
+
+/// Node kinds which are delimited by brackets (or backticks) and fold as a whole.
+static DELIMITED: &[&str] = &[
+    "arguments",
+    "array",
+    "array_pattern",
+    "class_body",
+    "formal_parameters",
+    "named_imports",
+    "object",
+    "object_pattern",
+    "statement_block",
+    "switch_body",
+    "template_string",
+];
+
+/// Client settings which restrict the folding ranges the server may report.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    pub line_folding_only: bool,
+    pub range_limit: Option<u32>,
+}
+
+impl Options {
+    pub fn new(capabilities: Option<&lsp::ClientCapabilities>) -> Self {
+        let capabilities = capabilities
+            .and_then(|capabilities| capabilities.text_document.as_ref())
+            .and_then(|capabilities| capabilities.folding_range.as_ref());
+        let line_folding_only = capabilities
+            .and_then(|capabilities| capabilities.line_folding_only)
+            .unwrap_or_default();
+        let range_limit = capabilities.and_then(|capabilities| capabilities.range_limit);
+        Options {
+            line_folding_only,
+            range_limit,
+        }
+    }
+}
+
+pub fn folding_ranges(content: &ropey::Rope, tree: &tree_sitter::Tree, options: Options) -> Vec<lsp::FoldingRange> {
+    let mut builder = Builder {
+        content,
+        options,
+        regions: vec![],
+        ranges: vec![],
+    };
+
+    let mut cursor = tree.walk();
+    'nodes: loop {
+        builder.visit(&cursor.node());
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if cursor.goto_next_sibling() {
+                continue 'nodes;
+            }
+            if !cursor.goto_parent() {
+                break 'nodes;
+            }
+        }
+    }
+
+    let mut ranges = builder.ranges;
+    ranges.sort_by_key(|range| (range.start_line, std::cmp::Reverse(range.end_line)));
+
+    // NOTE: clients which only fold whole lines can only show one range per line; keep the
+    // outermost one.
+    if options.line_folding_only {
+        ranges.dedup_by_key(|range| range.start_line);
+    }
+
+    if let Some(range_limit) = options.range_limit {
+        ranges.truncate(range_limit as usize);
+    }
+
+    ranges
+}
+
+struct Builder<'a> {
+    content: &'a ropey::Rope,
+    options: Options,
+    /// Start lines of the `#region` markers which have not been closed yet.
+    regions: Vec<u32>,
+    ranges: Vec<lsp::FoldingRange>,
+}
+
+impl<'a> Builder<'a> {
+    fn visit(&mut self, node: &tree_sitter::Node) {
+        let kind = node.kind();
+        match &*kind {
+            "comment" => self.visit_comment(node),
+            "program" => self.visit_imports(node),
+            kind if DELIMITED.contains(&kind) => {
+                let range = self.content.tree_sitter_range_to_lsp_range(node.range());
+                let end_line = if self.options.line_folding_only && self.is_closing_line(node) {
+                    range.end.line.saturating_sub(1)
+                } else {
+                    range.end.line
+                };
+                self.push(range, end_line, None);
+            },
+            _ => {},
+        }
+    }
+
+    fn visit_comment(&mut self, node: &tree_sitter::Node) {
+        let range = self.content.tree_sitter_range_to_lsp_range(node.range());
+        let text = self.content.utf8_text_for_tree_sitter_node(node);
+
+        if let Some(marker) = text.strip_prefix("//").map(str::trim_start) {
+            if marker.starts_with("#region") {
+                self.regions.push(range.start.line);
+            } else if marker.starts_with("#endregion") {
+                if let Some(start_line) = self.regions.pop() {
+                    let range = lsp::Range::new(lsp::Position::new(start_line, 0), range.end);
+                    let kind = Some(lsp::FoldingRangeKind::Region);
+                    self.push(range, range.end.line, kind);
+                }
+            }
+            return;
+        }
+
+        let kind = Some(lsp::FoldingRangeKind::Comment);
+        self.push(range, range.end.line, kind);
+    }
+
+    /// Fold each group of consecutive import statements at the top level of the program.
+    fn visit_imports(&mut self, program: &tree_sitter::Node) {
+        let mut group: Option<lsp::Range> = None;
+        for i in 0 .. program.named_child_count() {
+            let child = match program.named_child(i) {
+                Some(child) => child,
+                None => continue,
+            };
+            if child.kind() == "import_statement" {
+                let range = self.content.tree_sitter_range_to_lsp_range(child.range());
+                group = Some(match group {
+                    Some(group) => lsp::Range::new(group.start, range.end),
+                    None => range,
+                });
+            } else if child.kind() != "comment" {
+                if let Some(group) = group.take() {
+                    self.push(group, group.end.line, Some(lsp::FoldingRangeKind::Imports));
+                }
+            }
+        }
+        if let Some(group) = group {
+            self.push(group, group.end.line, Some(lsp::FoldingRangeKind::Imports));
+        }
+    }
+
+    fn push(&mut self, range: lsp::Range, end_line: u32, kind: Option<lsp::FoldingRangeKind>) {
+        if end_line <= range.start.line {
+            return;
+        }
+        let (start_character, end_character) = if self.options.line_folding_only {
+            (None, None)
+        } else {
+            (Some(range.start.character), Some(range.end.character))
+        };
+        self.ranges.push(lsp::FoldingRange {
+            start_line: range.start.line,
+            start_character,
+            end_line,
+            end_character,
+            kind,
+        });
+    }
+
+    /// Whether the closing delimiter of the node is the first thing on its line, in which case the
+    /// line should stay visible when the range is folded.
+    fn is_closing_line(&self, node: &tree_sitter::Node) -> bool {
+        let end = node.end_position();
+        let line = self.content.line(end.row() as usize).to_string();
+        let column = (end.column() as usize).saturating_sub(1);
+        line.get(.. column).map_or(false, |prefix| prefix.trim().is_empty())
+    }
+}
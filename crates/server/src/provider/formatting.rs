@@ -0,0 +1,531 @@
+use lsp_text::RopeExt;
+use std::{collections::HashMap, ops::Range};
+
+/// Node kinds whose contents are indented one level deeper than the line they start on.
+static CONTAINERS: &[&str] = &[
+    "arguments",
+    "array",
+    "array_pattern",
+    "class_body",
+    "export_clause",
+    "formal_parameters",
+    "named_imports",
+    "object",
+    "object_pattern",
+    "parenthesized_expression",
+    "statement_block",
+    "switch_body",
+];
+
+/// Node kinds which are formatted as a single token, leaving their contents untouched.
+static ATOMIC: &[&str] = &["comment", "hash_bang_line", "regex", "string", "template_string"];
+
+/// Node kinds whose operator tokens are surrounded by spaces.
+static OPERATOR_PARENTS: &[&str] = &[
+    "arrow_function",
+    "assignment_expression",
+    "assignment_pattern",
+    "augmented_assignment_expression",
+    "binary_expression",
+    "field_definition",
+    "public_field_definition",
+    "ternary_expression",
+    "variable_declarator",
+];
+
+/// A leaf of the syntax tree (or an atomic node) as seen by the formatter.
+struct Token<'tree> {
+    node: tree_sitter::Node<'tree>,
+    text: String,
+    kind: String,
+    parent: String,
+    /// Whether the whitespace around the token must be kept as it is, e.g., inside ERROR nodes.
+    verbatim: bool,
+}
+
+impl<'tree> Token<'tree> {
+    fn is(&self, text: &str) -> bool {
+        !self.node.is_named() && self.text == text
+    }
+
+    fn is_keyword(&self) -> bool {
+        !self.node.is_named() && self.text.chars().all(|c| c.is_ascii_alphabetic())
+    }
+
+    fn is_operator(&self) -> bool {
+        let is_symbolic = !self.node.is_named() && !self.text.chars().any(|c| "()[]{},;.".contains(c));
+        let is_word_operator = matches!(self.text.as_str(), "in" | "instanceof") && self.parent == "binary_expression";
+        OPERATOR_PARENTS.contains(&self.parent.as_str()) && (is_symbolic || is_word_operator)
+    }
+}
+
+/// Format the document, or only the lines starting within `range`, returning the edits to the
+/// whitespace between tokens which differ from the current text.
+pub fn formatting(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    options: &lsp::FormattingOptions,
+    range: Option<lsp::Range>,
+) -> anyhow::Result<Vec<lsp::TextEdit>> {
+    let range = match range {
+        Some(range) => {
+            let start = content.lsp_position_to_core(range.start)?.byte;
+            let end = content.lsp_position_to_core(range.end)?.byte;
+            Some(start .. end)
+        },
+        None => None,
+    };
+    Ok(edits(content, tree, options, range))
+}
+
+/// Compute the formatting edits for the gaps between tokens which end within `range` (or all of
+/// them).
+pub fn edits(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    options: &lsp::FormattingOptions,
+    range: Option<Range<u32>>,
+) -> Vec<lsp::TextEdit> {
+    let tokens = tokens(content, tree);
+    let mut formatter = Formatter::new(content, options);
+
+    let mut edits = vec![];
+    let mut previous: Option<&Token> = None;
+    for token in tokens.iter().map(Some).chain(std::iter::once(None)) {
+        let start = previous.map_or(0, |previous| previous.node.end_byte());
+        let end = token.map_or(content.len_bytes() as u32, |token| token.node.start_byte());
+        let old = content.byte_slice(start as usize .. end as usize).to_string();
+
+        let new = formatter.gap(previous, token, &old);
+        previous = token;
+
+        let in_range = range.as_ref().map_or(true, |range| range.start <= end && end <= range.end);
+        if in_range {
+            if new != old {
+                edits.push(formatter.edit(start, &old, &new));
+            }
+        } else if let Some(token) = token.filter(|_| old.contains('\n')) {
+            // NOTE: lines outside of the range keep their indentation, so nested lines within the
+            // range are indented relative to it.
+            formatter.indents.insert(token.node.start_position().row(), original_indent(&old));
+        }
+    }
+
+    edits
+}
+
+/// Compute the indentation for a blank line at the offset from the innermost enclosing container.
+pub fn indent_at(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    options: &lsp::FormattingOptions,
+    byte: u32,
+) -> String {
+    let formatter = Formatter::new(content, options);
+    let node = tree.root_node().descendant_for_byte_range(byte, byte);
+    let container = std::iter::successors(node, |node| node.parent()).find(|node| {
+        let is_inside = node.start_byte() < byte && (byte < node.end_byte() || node.has_error());
+        CONTAINERS.contains(&&*node.kind()) && is_inside
+    });
+    match container {
+        Some(container) => formatter.indent_of_row(container.start_position().row()) + &formatter.unit,
+        None => String::new(),
+    }
+}
+
+/// Collect the tokens of the document in order.
+fn tokens<'tree>(content: &ropey::Rope, tree: &'tree tree_sitter::Tree) -> Vec<Token<'tree>> {
+    let mut tokens: Vec<Token> = vec![];
+    // NOTE: the whitespace next to a MISSING node is kept since inserting the node may need it.
+    let mut verbatim_next = false;
+
+    let mut cursor = tree.walk();
+    'nodes: loop {
+        let node = cursor.node();
+        let kind = node.kind();
+        let is_atomic = ATOMIC.contains(&&*kind);
+
+        if node.is_missing() {
+            if let Some(token) = tokens.last_mut() {
+                token.verbatim = true;
+            }
+            verbatim_next = true;
+        } else if is_atomic || node.child_count() == 0 {
+            let verbatim = verbatim_next
+                || std::iter::successors(Some(node.clone()), |node| node.parent())
+                    .any(|ancestor| ancestor.is_error() || ancestor.kind().starts_with("jsx"));
+            verbatim_next = false;
+            tokens.push(Token {
+                node: node.clone(),
+                text: content.utf8_text_for_tree_sitter_node(&node).to_string(),
+                kind: kind.to_string(),
+                parent: node.parent().map(|parent| parent.kind().to_string()).unwrap_or_default(),
+                verbatim,
+            });
+        }
+
+        if !is_atomic && cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if cursor.goto_next_sibling() {
+                continue 'nodes;
+            }
+            if !cursor.goto_parent() {
+                break 'nodes;
+            }
+        }
+    }
+
+    tokens
+}
+
+struct Formatter<'a> {
+    content: &'a ropey::Rope,
+    options: &'a lsp::FormattingOptions,
+    unit: String,
+    eol: &'static str,
+    /// The (formatted) indentation of each line, by row.
+    indents: HashMap<u32, String>,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(content: &'a ropey::Rope, options: &'a lsp::FormattingOptions) -> Self {
+        let unit = if options.insert_spaces {
+            " ".repeat(options.tab_size as usize)
+        } else {
+            "\t".into()
+        };
+        let eol = if content.chunks().any(|chunk| chunk.contains("\r\n")) {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        Formatter {
+            content,
+            options,
+            unit,
+            eol,
+            indents: HashMap::new(),
+        }
+    }
+
+    /// Compute the new text of the gap between two tokens.
+    fn gap(&mut self, previous: Option<&Token>, next: Option<&Token>, old: &str) -> String {
+        // NOTE: never touch anything but whitespace.
+        if !old.chars().all(char::is_whitespace) {
+            return old.to_string();
+        }
+        if previous.map_or(false, |previous| previous.verbatim) || next.map_or(false, |next| next.verbatim) {
+            if let Some(next) = next.filter(|_| old.contains('\n')) {
+                self.indents.insert(next.node.start_position().row(), original_indent(old));
+            }
+            return old.to_string();
+        }
+
+        let newlines = old.matches('\n').count();
+        let trim = self.options.trim_trailing_whitespace != Some(false);
+        let trailing = if trim { "" } else { old.split('\n').next().unwrap_or_default() };
+
+        match (previous, next) {
+            // NOTE: the start of the document.
+            (None, Some(next)) => {
+                self.indents.insert(next.node.start_position().row(), String::new());
+                String::new()
+            },
+            // NOTE: the end of the document.
+            (Some(_), None) => {
+                let mut newlines = newlines;
+                if self.options.trim_final_newlines == Some(true) {
+                    newlines = newlines.min(1);
+                }
+                if self.options.insert_final_newline == Some(true) {
+                    newlines = newlines.max(1);
+                }
+                if newlines == 0 {
+                    trailing.to_string()
+                } else {
+                    format!("{}{}", trailing.trim_end_matches('\r'), self.eol.repeat(newlines))
+                }
+            },
+            (Some(_), Some(next)) if newlines > 0 => {
+                let indent = self.indent(next);
+                self.indents.insert(next.node.start_position().row(), indent.clone());
+                // NOTE: keep at most one blank line.
+                let newlines = newlines.min(2);
+                format!("{}{}{}", trailing.trim_end_matches('\r'), self.eol.repeat(newlines), indent)
+            },
+            (Some(previous), Some(next)) => match spacing(previous, next) {
+                Some(true) => " ".into(),
+                Some(false) if old.is_empty() || !needs_separator(previous, next) => String::new(),
+                _ => old.to_string(),
+            },
+            (None, None) => old.to_string(),
+        }
+    }
+
+    /// Compute the indentation of the line starting with the token.
+    fn indent(&self, token: &Token) -> String {
+        let node = token.node.clone();
+        let row = node.start_position().row();
+
+        let mut container = None;
+        let mut closing = false;
+        for ancestor in std::iter::successors(node.parent(), |node| node.parent()) {
+            if !CONTAINERS.contains(&&*ancestor.kind()) || ancestor.start_byte() == node.start_byte() {
+                continue;
+            }
+            closing = ancestor.end_byte() == node.end_byte() && !node.is_named();
+            container = Some(ancestor);
+            break;
+        }
+
+        let mut indent = match &container {
+            Some(container) => {
+                let mut indent = self.indent_of_row(container.start_position().row());
+                if !closing {
+                    indent.push_str(&self.unit);
+                }
+                indent
+            },
+            None => String::new(),
+        };
+
+        // NOTE: lines continuing a statement (or expression) which started on an earlier line are
+        // indented once more, except for clauses like `else` which continue their statement.
+        if !closing {
+            let continues = std::iter::successors(Some(node.clone()), |node| node.parent())
+                .take_while(|ancestor| Some(ancestor) != container.as_ref() && ancestor.kind() != "program")
+                .take_while(|ancestor| {
+                    let is_clause = matches!(&*ancestor.kind(), "else_clause" | "catch_clause" | "finally_clause");
+                    !(is_clause && ancestor.start_byte() == node.start_byte())
+                })
+                .any(|ancestor| ancestor.start_position().row() < row);
+            let is_do_while = token.is("while") && token.parent == "do_statement";
+            if continues && !is_do_while {
+                indent.push_str(&self.unit);
+            }
+        }
+
+        indent
+    }
+
+    fn indent_of_row(&self, row: u32) -> String {
+        self.indents.get(&row).cloned().unwrap_or_else(|| {
+            let line = self.content.line(row as usize);
+            line.chars().take_while(|&c| c == ' ' || c == '\t').collect()
+        })
+    }
+
+    /// Build the edit replacing the gap, trimming the common prefix and suffix.
+    fn edit(&self, start: u32, old: &str, new: &str) -> lsp::TextEdit {
+        let prefix = old
+            .char_indices()
+            .zip(new.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, c), _)| i + c.len_utf8());
+        let (old_rest, new_rest) = (&old[prefix ..], &new[prefix ..]);
+        let suffix = old_rest
+            .chars()
+            .rev()
+            .zip(new_rest.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum::<usize>();
+
+        let start = start as usize + prefix;
+        let end = start + old_rest.len() - suffix;
+        let range = lsp::Range::new(
+            self.content.byte_to_lsp_position(start),
+            self.content.byte_to_lsp_position(end),
+        );
+        let new_text = new_rest[.. new_rest.len() - suffix].to_string();
+        lsp::TextEdit { range, new_text }
+    }
+}
+
+/// The indentation following the last line break in the text.
+fn original_indent(text: &str) -> String {
+    let line = text.rsplit('\n').next().unwrap_or_default();
+    line.chars().take_while(|&c| c == ' ' || c == '\t').collect()
+}
+
+/// Decide whether a space separates two tokens on the same line. Returns [`None`] where the
+/// existing whitespace is kept.
+fn spacing(a: &Token, b: &Token) -> Option<bool> {
+    if a.kind == "comment" {
+        return None;
+    }
+    if a.is("(") || a.is("[") || b.is(")") || b.is("]") {
+        return Some(false);
+    }
+    if b.is(",") || b.is(";") {
+        return Some(false);
+    }
+    if a.is(",") || a.is(";") {
+        return Some(true);
+    }
+    if a.is(".") || a.is("?.") || b.is(".") || b.is("?.") || a.is("...") {
+        return Some(false);
+    }
+    if b.kind == "comment" {
+        return Some(true);
+    }
+
+    // `function* gen`, `yield* gen`, `*gen() {}`, `import * as ns`
+    if a.is("*") && a.parent != "binary_expression" {
+        return Some(a.parent != "method_definition");
+    }
+    if b.is("*") && b.parent != "binary_expression" {
+        let is_namespace = matches!(b.parent.as_str(), "namespace_import" | "export_statement");
+        return Some(is_namespace || (a.is_keyword() && b.parent == "method_definition"));
+    }
+
+    if a.is_operator() || b.is_operator() {
+        return Some(true);
+    }
+    let is_label = |parent: &str| matches!(parent, "pair" | "pair_pattern" | "labeled_statement" | "switch_case");
+    if a.is(":") && is_label(&a.parent) {
+        return Some(true);
+    }
+    if b.is(":") && is_label(&b.parent) {
+        return Some(false);
+    }
+    // `!a`, `-a`, `a++`, `++a`
+    if !a.node.is_named() && !a.is_keyword() && matches!(a.parent.as_str(), "unary_expression" | "update_expression") {
+        return Some(false);
+    }
+    if !b.node.is_named() && b.parent == "update_expression" {
+        return Some(false);
+    }
+
+    if b.is("(") {
+        // NOTE: calls and parameter lists, except for parameters following a keyword, like
+        // `function ()`, `async ()` or `return (a) => a`.
+        if matches!(b.parent.as_str(), "arguments" | "formal_parameters") {
+            return Some(a.is_keyword() && b.parent == "formal_parameters");
+        }
+        return if a.is_keyword() { Some(true) } else { None };
+    }
+    if b.is("[") && b.parent == "subscript_expression" {
+        return Some(false);
+    }
+    if b.is("{") {
+        return Some(true);
+    }
+    if a.is("{") {
+        return Some(!b.is("}"));
+    }
+    if b.is("}") {
+        return Some(true);
+    }
+
+    if a.is_keyword() || b.is_keyword() {
+        return Some(true);
+    }
+    let is_word = |c: Option<char>| c.map_or(false, |c| c == '$' || c == '_' || c.is_alphanumeric());
+    if is_word(a.text.chars().last()) && is_word(b.text.chars().next()) {
+        return Some(true);
+    }
+
+    None
+}
+
+/// Whether removing the whitespace between the tokens would change how the text is tokenized,
+/// e.g., `a - -b` or `1 .toString()`.
+fn needs_separator(a: &Token, b: &Token) -> bool {
+    let last = a.text.chars().last().unwrap_or_default();
+    let first = b.text.chars().next().unwrap_or_default();
+    let is_word = |c: char| c == '$' || c == '_' || c.is_alphanumeric();
+    (is_word(last) && is_word(first))
+        || (last == '+' && first == '+')
+        || (last == '-' && first == '-')
+        || (last == '/' && (first == '/' || first == '*'))
+        || (last == '*' && first == '/')
+        || (last == '<' && first == '!')
+        || (last == '-' && first == '>')
+        || (a.kind == "number" && first == '.')
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::formatting;
+    use crate::core::test::{apply, parse};
+
+    fn options() -> lsp::FormattingOptions {
+        lsp::FormattingOptions {
+            tab_size: 2,
+            insert_spaces: true,
+            ..Default::default()
+        }
+    }
+
+    fn format(source: &str) -> String {
+        let (content, tree) = parse(source);
+        let edits = formatting(&content, &tree, &options(), None).unwrap();
+        apply(&content, &edits)
+    }
+
+    #[test]
+    fn keeps_space_between_keyword_and_parameters() {
+        assert_eq!(format("function f(){return (a)=>a}\n"), "function f() { return (a) => a }\n");
+        assert_eq!(format("export default (a)=>a;\n"), "export default (a) => a;\n");
+        assert_eq!(format("let f = async (a)=>a;\n"), "let f = async (a) => a;\n");
+        assert_eq!(format("let f = function (a){};\n"), "let f = function (a) {};\n");
+    }
+
+    #[test]
+    fn removes_space_before_arguments_and_parameters() {
+        assert_eq!(format("f (a);new Foo (b);super.x (c)\n"), "f(a); new Foo(b); super.x(c)\n");
+        assert_eq!(format("function f (a,b) {}\n"), "function f(a, b) {}\n");
+    }
+
+    #[test]
+    fn formats_blocks() {
+        let source = "if(a){\nb()\n}else{\n    c( )\n}\n";
+        assert_eq!(format(source), "if (a) {\n  b()\n} else {\n  c()\n}\n");
+    }
+
+    #[test]
+    fn is_idempotent() {
+        let source = concat!(
+            "import {a,b} from 'c'\n",
+            "class A extends B{\n",
+            "constructor( x ){super(x);this.x=x}\n",
+            "get y(){return {a:1,b:[1,2,3]}}\n",
+            "}\n",
+            "for(let i=0;i<10;i++){\n",
+            "  if(!a&&b){continue}\n",
+            "}\n",
+        );
+        let formatted = format(source);
+        assert_ne!(formatted, source);
+        assert_eq!(format(&formatted), formatted);
+    }
+
+    #[test]
+    fn formats_only_the_range() {
+        let source = "if(a){\nb( )\n}else{\n    c( )\n}\n";
+        let (content, tree) = parse(source);
+        let range = lsp::Range::new(lsp::Position::new(3, 0), lsp::Position::new(4, 0));
+        let edits = formatting(&content, &tree, &options(), Some(range)).unwrap();
+        assert_eq!(apply(&content, &edits), "if(a){\nb( )\n}else{\n  c()\n}\n");
+    }
+
+    #[test]
+    fn keeps_errors_verbatim() {
+        // NOTE: the missing initializer and the unbalanced call are parsed as MISSING and ERROR nodes.
+        assert_eq!(format("let a =  ;\nfoo(  1,2)\n"), "let a =  ;\nfoo(1, 2)\n");
+        assert_eq!(format("foo( ( 1 ;\nbar( 2 )\n"), "foo( ( 1 ;\nbar(2)\n");
+    }
+
+    #[test]
+    fn keeps_comments() {
+        // NOTE: the whitespace following a comment on the same line is kept.
+        assert_eq!(format("a;   // note\n/*  block  */  b\n"), "a; // note\n/*  block  */  b\n");
+        assert_eq!(format("f(a,/* b */c)\n"), "f(a, /* b */c)\n");
+        assert_eq!(format("{\n// note\na()\n}\n"), "{\n  // note\n  a()\n}\n");
+    }
+}
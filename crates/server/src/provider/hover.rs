@@ -0,0 +1,308 @@
+use crate::core::{
+    declaration::{self, JsDoc},
+    scope::Scopes,
+};
+use lsp_text::RopeExt;
+
+/// Whether the client prefers Markdown for hover contents.
+pub fn supports_markdown(capabilities: Option<&lsp::ClientCapabilities>) -> bool {
+    capabilities
+        .and_then(|capabilities| capabilities.text_document.as_ref())
+        .and_then(|capabilities| capabilities.hover.as_ref())
+        .and_then(|capabilities| capabilities.content_format.as_ref())
+        .map_or(false, |formats| formats.first() == Some(&lsp::MarkupKind::Markdown))
+}
+
+/// A part of the hover contents.
+enum Section {
+    Code(String),
+    Text(String),
+    /// A decoded literal value, shown verbatim.
+    Value(String),
+    Doc(JsDoc),
+}
+
+pub fn hover(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+    markdown: bool,
+) -> anyhow::Result<Option<lsp::Hover>> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+    let node = match tree.root_node().named_descendant_for_byte_range(byte, byte) {
+        Some(node) => node,
+        None => return Ok(None),
+    };
+
+    let (range, sections) = match literal(content, node.clone()).or_else(|| declaration(content, tree, node, byte)) {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+
+    let value = render(sections, markdown);
+    let kind = if markdown {
+        lsp::MarkupKind::Markdown
+    } else {
+        lsp::MarkupKind::PlainText
+    };
+    Ok(Some(lsp::Hover {
+        contents: lsp::HoverContents::Markup(lsp::MarkupContent { kind, value }),
+        range: Some(range),
+    }))
+}
+
+fn render(sections: Vec<Section>, markdown: bool) -> String {
+    let sections = sections.into_iter().filter_map(|section| {
+        let text = match section {
+            Section::Code(code) if markdown => format!("```javascript\n{}\n```", code),
+            Section::Code(code) => code,
+            Section::Text(text) => text,
+            Section::Value(value) if markdown => format!("```text\n{}\n```", value),
+            Section::Value(value) => value,
+            Section::Doc(doc) if markdown => doc.to_markdown(),
+            Section::Doc(doc) => doc.to_plaintext(),
+        };
+        Some(text).filter(|text| !text.is_empty())
+    });
+    let separator = if markdown { "\n\n---\n\n" } else { "\n\n" };
+    sections.collect::<Vec<_>>().join(separator)
+}
+
+/// Describe the number, string or regex literal at the node, returning the range of the literal.
+fn literal(content: &ropey::Rope, node: tree_sitter::Node) -> Option<(lsp::Range, Vec<Section>)> {
+    let node = match &*node.kind() {
+        "escape_sequence" | "string_fragment" | "regex_pattern" | "regex_flags" => node.parent()?,
+        _ => node,
+    };
+    let text = content.utf8_text_for_tree_sitter_node(&node);
+
+    let sections = match &*node.kind() {
+        "number" => {
+            let value = decode_number(&text)?;
+            vec![Section::Code(format!("(number) {}", value))]
+        },
+        "string" => string_sections(decode_string(&text)?),
+        // NOTE: templates with substitutions have no single value.
+        "template_string" if !has_substitution(&node) => string_sections(decode_string(&text)?),
+        "regex" => {
+            let pattern = node.child_by_field_name("pattern");
+            let pattern = pattern.map(|pattern| content.utf8_text_for_tree_sitter_node(&pattern).to_string());
+            let flags = node.child_by_field_name("flags");
+            let flags = flags.map(|flags| content.utf8_text_for_tree_sitter_node(&flags).to_string());
+            let mut sections = vec![Section::Code(format!("(regex) /{}/", pattern.unwrap_or_default()))];
+            let flags = describe_regex_flags(&flags.unwrap_or_default());
+            if !flags.is_empty() {
+                sections.push(Section::Text(format!("Flags: {}", flags.join(", "))));
+            }
+            sections
+        },
+        _ => return None,
+    };
+
+    Some((content.tree_sitter_range_to_lsp_range(node.range()), sections))
+}
+
+fn string_sections(value: String) -> Vec<Section> {
+    let length = value.encode_utf16().count();
+    vec![
+        Section::Code(format!("(string) length {}", length)),
+        Section::Value(value),
+    ]
+}
+
+fn has_substitution(node: &tree_sitter::Node) -> bool {
+    (0 .. node.named_child_count())
+        .filter_map(|i| node.named_child(i))
+        .any(|child| child.kind() == "template_substitution")
+}
+
+/// Describe the binding of the identifier at the byte offset, or the method named by the node,
+/// returning the range of the identifier.
+fn declaration(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    node: tree_sitter::Node,
+    byte: u32,
+) -> Option<(lsp::Range, Vec<Section>)> {
+    if let Some(method) = node.parent().filter(|parent| parent.kind() == "method_definition") {
+        if method.child_by_field_name("name").as_ref() == Some(&node) {
+            let mut sections = vec![Section::Code(declaration::function_signature(content, &method))];
+            sections.extend(declaration::documentation(content, &method).map(Section::Doc));
+            return Some((content.tree_sitter_range_to_lsp_range(node.range()), sections));
+        }
+    }
+
+    // NOTE: the range is that of the occurrence the binding was resolved from, which is not
+    // necessarily the innermost node at the offset, e.g., at the end of an identifier.
+    let scopes = Scopes::analyze(content, tree);
+    let occurrence = scopes.occurrence_at(byte)?;
+    let binding = &scopes.bindings[scopes.binding_of(occurrence)?];
+    let mut sections = vec![Section::Code(declaration::signature(content, tree, binding))];
+    let documentation = declaration::node(tree, binding);
+    let documentation = documentation.and_then(|node| declaration::documentation(content, &node));
+    sections.extend(documentation.map(Section::Doc));
+    Some((scopes.range_of(occurrence), sections))
+}
+
+/// Decode the value of a number literal, e.g., `0xff` or `1_000n`.
+pub fn decode_number(text: &str) -> Option<String> {
+    let text = text.replace('_', "");
+    if let Some(digits) = text.strip_suffix('n') {
+        return parse_integer(digits).map(|value| format!("{}n", value));
+    }
+    if let Some(value) = parse_integer(&text) {
+        return Some(value.to_string());
+    }
+    text.parse::<f64>().ok().map(|value| value.to_string())
+}
+
+fn parse_integer(text: &str) -> Option<u128> {
+    let lower = text.to_ascii_lowercase();
+    let (radix, digits) = if let Some(digits) = lower.strip_prefix("0x") {
+        (16, digits)
+    } else if let Some(digits) = lower.strip_prefix("0o") {
+        (8, digits)
+    } else if let Some(digits) = lower.strip_prefix("0b") {
+        (2, digits)
+    } else if lower.len() > 1 && lower.starts_with('0') && lower.bytes().all(|byte| matches!(byte, b'0' ..= b'7')) {
+        // NOTE: legacy octal literals, e.g., `0755`.
+        (8, &lower[1 ..])
+    } else {
+        (10, lower.as_str())
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    u128::from_str_radix(digits, radix).ok()
+}
+
+/// Decode the value of a string literal (or of a template string without substitutions),
+/// including its quotes.
+pub fn decode_string(text: &str) -> Option<String> {
+    let quote = text.chars().next()?;
+    let inner = text.strip_prefix(quote)?.strip_suffix(quote)?;
+
+    // NOTE: escapes produce UTF-16 code units so that surrogate pairs, e.g., `\uD83D\uDE00`, combine.
+    let mut units: Vec<u16> = vec![];
+    let mut chars = inner.chars().peekable();
+    let mut buffer = [0; 2];
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            units.extend_from_slice(c.encode_utf16(&mut buffer));
+            continue;
+        }
+        let escaped = chars.next()?;
+        let unit = match escaped {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            '0' if !chars.peek().map_or(false, char::is_ascii_digit) => '\0',
+            'x' => {
+                let digits: String = chars.by_ref().take(2).collect();
+                units.push(u16::from_str_radix(&digits, 16).ok()?);
+                continue;
+            },
+            'u' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let digits: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let c = char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?;
+                units.extend_from_slice(c.encode_utf16(&mut buffer));
+                continue;
+            },
+            'u' => {
+                let digits: String = chars.by_ref().take(4).collect();
+                units.push(u16::from_str_radix(&digits, 16).ok()?);
+                continue;
+            },
+            // NOTE: line continuations.
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                continue;
+            },
+            '\n' | '\u{2028}' | '\u{2029}' => continue,
+            other => other,
+        };
+        units.extend_from_slice(unit.encode_utf16(&mut buffer));
+    }
+
+    Some(String::from_utf16_lossy(&units))
+}
+
+/// Describe each flag of a regex literal.
+fn describe_regex_flags(flags: &str) -> Vec<&'static str> {
+    flags
+        .chars()
+        .filter_map(|flag| match flag {
+            'd' => Some("`d` has indices"),
+            'g' => Some("`g` global"),
+            'i' => Some("`i` ignore case"),
+            'm' => Some("`m` multiline"),
+            's' => Some("`s` dot all"),
+            'u' => Some("`u` unicode"),
+            'v' => Some("`v` unicode sets"),
+            'y' => Some("`y` sticky"),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_number, decode_string};
+
+    #[test]
+    fn numbers() {
+        assert_eq!(Some("255".into()), decode_number("0xff"));
+        assert_eq!(Some("493".into()), decode_number("0o755"));
+        assert_eq!(Some("493".into()), decode_number("0755"));
+        assert_eq!(Some("5".into()), decode_number("0b101"));
+        assert_eq!(Some("1000000".into()), decode_number("1_000_000"));
+        assert_eq!(Some("1000".into()), decode_number("1e3"));
+        assert_eq!(Some("0.5".into()), decode_number(".5"));
+        assert_eq!(Some("255n".into()), decode_number("0xffn"));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(Some("a\nb".into()), decode_string(r#""a\nb""#));
+        assert_eq!(Some("it's".into()), decode_string(r"'it\'s'"));
+        assert_eq!(Some("A".into()), decode_string(r"'\x41'"));
+        assert_eq!(Some("é".into()), decode_string(r"'é'"));
+        assert_eq!(Some("😀".into()), decode_string(r"'\u{1F600}'"));
+        assert_eq!(Some("😀".into()), decode_string(r"'\uD83D\uDE00'"));
+        assert_eq!(Some("ab".into()), decode_string("'a\\\nb'"));
+        assert_eq!(Some("x".into()), decode_string("`x`"));
+    }
+}
+
+#[cfg(all(test, feature = "native"))]
+mod parsing_tests {
+    use super::hover;
+    use crate::core::test::{parse, position};
+
+    fn range(source: &str, at: lsp::Position) -> Option<lsp::Range> {
+        let (content, tree) = parse(source);
+        hover(&content, &tree, at, false).unwrap()?.range
+    }
+
+    #[test]
+    fn ranges_cover_the_occurrence() {
+        let source = "let abc = 1;\nf(abc);\n";
+        let expected = lsp::Range::new(lsp::Position::new(1, 2), lsp::Position::new(1, 5));
+        assert_eq!(range(source, lsp::Position::new(1, 3)), Some(expected));
+        // NOTE: the innermost node at the end of the identifier is the argument list.
+        assert_eq!(range(source, lsp::Position::new(1, 5)), Some(expected));
+    }
+
+    #[test]
+    fn ranges_cover_the_literal() {
+        let (content, _) = parse("f('a\\nb', 0xff);\n");
+        let expected = lsp::Range::new(lsp::Position::new(0, 2), lsp::Position::new(0, 8));
+        assert_eq!(range("f('a\\nb', 0xff);\n", position(&content, "nb")), Some(expected));
+    }
+}
@@ -0,0 +1,448 @@
+use crate::core::{
+    declaration,
+    scope::Scopes,
+    settings::{InlayHintSettings, ParameterNameHints},
+};
+use lsp_text::RopeExt;
+
+/// The number of items from which an array literal gets index hints.
+pub static LONG_ARRAY: u32 = 10;
+
+/// Default values longer than this are elided in hints.
+static MAX_VALUE_LENGTH: usize = 24;
+
+pub fn inlay_hints(
+    uri: &lsp::Url,
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    range: lsp::Range,
+    settings: InlayHintSettings,
+) -> anyhow::Result<Vec<lsp::InlayHint>> {
+    let start = content.lsp_position_to_core(range.start)?.byte;
+    let end = content.lsp_position_to_core(range.end)?.byte;
+    let scopes = Scopes::analyze(content, tree);
+    let builder = Builder {
+        uri,
+        content,
+        tree,
+        scopes: &scopes,
+        settings,
+    };
+
+    let mut hints = vec![];
+    let mut cursor = tree.walk();
+    'nodes: loop {
+        let node = cursor.node();
+        let overlaps = node.start_byte() <= end && start <= node.end_byte();
+
+        if overlaps {
+            match &*node.kind() {
+                "call_expression" | "new_expression" => builder.call(&node, &mut hints),
+                "array" => builder.array(&node, &mut hints),
+                _ => {},
+            }
+        }
+
+        if overlaps && cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if cursor.goto_next_sibling() {
+                continue 'nodes;
+            }
+            if !cursor.goto_parent() {
+                break 'nodes;
+            }
+        }
+    }
+
+    hints.retain(|hint| range.start <= hint.position && hint.position <= range.end);
+    Ok(hints)
+}
+
+/// Return the document URI stored in the data of an inlay hint.
+pub fn data_uri(hint: &lsp::InlayHint) -> Option<lsp::Url> {
+    let uri = hint.data.as_ref()?.get("uri")?.as_str()?;
+    lsp::Url::parse(uri).ok()
+}
+
+/// Fill in the tooltip of an inlay hint for an argument, which shows the signature of the called
+/// function and the documentation of the parameter.
+pub fn resolve(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    mut hint: lsp::InlayHint,
+    markdown: bool,
+) -> lsp::InlayHint {
+    let data = match hint.data.as_ref() {
+        Some(data) => data,
+        None => return hint,
+    };
+    let call = data.get("call").and_then(|call| call.as_u64()).map(|call| call as u32);
+    let index = data.get("parameter").and_then(|index| index.as_u64()).map(|index| index as usize);
+    let (call, index) = match call.zip(index) {
+        Some(data) => data,
+        None => return hint,
+    };
+
+    let scopes = Scopes::analyze(content, tree);
+    let callee = tree
+        .root_node()
+        .descendant_for_byte_range(call, call)
+        .and_then(|node| {
+            std::iter::successors(Some(node), |node| node.parent()).find(|node| {
+                node.start_byte() == call && matches!(&*node.kind(), "call_expression" | "new_expression")
+            })
+        })
+        .and_then(|call| call.child_by_field_name(callee_field(&call)))
+        .and_then(|callee| declaration::callee(content, tree, &scopes, &callee));
+    // NOTE: the document may have changed since the hint was computed.
+    let callee = match callee {
+        Some(callee) => callee,
+        None => return hint,
+    };
+
+    let signature = declaration::function_signature(content, &callee.function);
+    let name = declaration::parameters(&callee.function)
+        .get(index)
+        .and_then(|parameter| declaration::parameter_name(content, parameter));
+    let description = declaration::documentation(content, &callee.declaration)
+        .zip(name)
+        .and_then(|(documentation, name)| documentation.param(name.trim_start_matches("...")).map(String::from))
+        .filter(|description| !description.is_empty());
+
+    let (kind, mut value) = if markdown {
+        (lsp::MarkupKind::Markdown, format!("```javascript\n{}\n```", signature))
+    } else {
+        (lsp::MarkupKind::PlainText, signature)
+    };
+    if let Some(description) = description {
+        value.push_str("\n\n");
+        value.push_str(&description);
+    }
+    hint.tooltip = Some(lsp::InlayHintTooltip::MarkupContent(lsp::MarkupContent { kind, value }));
+    hint
+}
+
+fn callee_field(call: &tree_sitter::Node) -> &'static str {
+    if call.kind() == "new_expression" {
+        "constructor"
+    } else {
+        "function"
+    }
+}
+
+struct Builder<'a> {
+    uri: &'a lsp::Url,
+    content: &'a ropey::Rope,
+    tree: &'a tree_sitter::Tree,
+    scopes: &'a Scopes,
+    settings: InlayHintSettings,
+}
+
+impl<'a> Builder<'a> {
+    fn text(&self, node: &tree_sitter::Node) -> String {
+        self.content.utf8_text_for_tree_sitter_node(node).to_string()
+    }
+
+    fn position(&self, byte: u32) -> lsp::Position {
+        self.content.byte_to_lsp_position(byte as usize)
+    }
+
+    fn hint(&self, byte: u32, label: String, kind: Option<lsp::InlayHintKind>) -> lsp::InlayHint {
+        lsp::InlayHint {
+            position: self.position(byte),
+            label: lsp::InlayHintLabel::String(label),
+            kind,
+            text_edits: None,
+            tooltip: None,
+            padding_left: None,
+            padding_right: None,
+            data: None,
+        }
+    }
+
+    /// Compute the hints for the arguments of a call: the names of the parameters they are passed
+    /// to, and the default values of the parameters they leave out.
+    fn call(&self, call: &tree_sitter::Node, hints: &mut Vec<lsp::InlayHint>) {
+        let arguments = match call.child_by_field_name("arguments") {
+            Some(arguments) if arguments.kind() == "arguments" => arguments,
+            _ => return,
+        };
+        let callee = call
+            .child_by_field_name(callee_field(call))
+            .and_then(|callee| declaration::callee(self.content, self.tree, self.scopes, &callee));
+        let callee = match callee {
+            Some(callee) => callee,
+            None => return,
+        };
+
+        let parameters = declaration::parameters(&callee.function);
+        let rest = parameters
+            .iter()
+            .position(|parameter| parameter.kind() == "rest_pattern")
+            .unwrap_or(parameters.len());
+        let arguments_list = (0 .. arguments.named_child_count())
+            .filter_map(|i| arguments.named_child(i))
+            .filter(|argument| argument.kind() != "comment")
+            .collect::<Vec<_>>();
+        let data = |index: usize| {
+            serde_json::json!({
+                "uri": self.uri,
+                "call": call.start_byte(),
+                "parameter": index,
+            })
+        };
+
+        for (i, argument) in arguments_list.iter().enumerate() {
+            // NOTE: the parameters of the arguments after a spread are unknown.
+            if argument.kind() == "spread_element" {
+                return;
+            }
+            let index = i.min(rest);
+            let parameter = match parameters.get(index) {
+                Some(parameter) => parameter,
+                None => break,
+            };
+
+            if self.settings.destructured_defaults && argument.kind() == "object" {
+                self.omitted_properties(argument, parameter, data(index), hints);
+            }
+
+            // NOTE: only the first argument passed to a rest parameter is labelled.
+            if i > rest || !self.wants_name(argument) {
+                continue;
+            }
+            let name = match declaration::parameter_name(self.content, parameter) {
+                Some(name) => name,
+                None => continue,
+            };
+            let text = self.text(argument);
+            let plain_name = name.trim_start_matches("...");
+            if text == plain_name || text.ends_with(&format!(".{}", plain_name)) {
+                continue;
+            }
+            let mut hint = self.hint(argument.start_byte(), format!("{}:", name), Some(lsp::InlayHintKind::PARAMETER));
+            hint.padding_right = Some(true);
+            hint.data = Some(data(index));
+            hints.push(hint);
+        }
+
+        // NOTE: the parameters left out of the call take their default values.
+        if self.settings.destructured_defaults {
+            let close = arguments.end_byte().saturating_sub(1);
+            if self.text(&arguments).ends_with(')') {
+                for (index, parameter) in parameters.iter().enumerate().take(rest).skip(arguments_list.len()) {
+                    let label = match default_value(self.content, parameter) {
+                        Some(label) if index > 0 => format!(", {}", label),
+                        Some(label) => label,
+                        None => continue,
+                    };
+                    let mut hint = self.hint(close, label, Some(lsp::InlayHintKind::PARAMETER));
+                    hint.data = Some(data(index));
+                    hints.push(hint);
+                }
+            }
+        }
+    }
+
+    /// Whether the argument gets a hint naming its parameter.
+    fn wants_name(&self, argument: &tree_sitter::Node) -> bool {
+        match self.settings.parameter_names {
+            ParameterNameHints::None => false,
+            ParameterNameHints::Literals => is_literal(argument),
+            ParameterNameHints::All => true,
+        }
+    }
+
+    /// Compute the hints for the destructured properties with default values which an object
+    /// literal passed to the parameter leaves out.
+    fn omitted_properties(
+        &self,
+        object: &tree_sitter::Node,
+        parameter: &tree_sitter::Node,
+        data: serde_json::Value,
+        hints: &mut Vec<lsp::InlayHint>,
+    ) {
+        let pattern = match &*parameter.kind() {
+            "assignment_pattern" => parameter.child_by_field_name("left"),
+            _ => Some(parameter.clone()),
+        };
+        let pattern = match pattern {
+            Some(pattern) if pattern.kind() == "object_pattern" => pattern,
+            _ => return,
+        };
+
+        let mut keys = vec![];
+        for i in 0 .. object.named_child_count() {
+            let property = match object.named_child(i) {
+                Some(property) => property,
+                None => continue,
+            };
+            let key = match &*property.kind() {
+                "pair" | "method_definition" => property
+                    .child_by_field_name("key")
+                    .or_else(|| property.child_by_field_name("name"))
+                    .map(|key| property_key(self.content, &key)),
+                "shorthand_property_identifier" => Some(self.text(&property)),
+                "comment" => continue,
+                // NOTE: a spread may provide any of the properties.
+                _ => return,
+            };
+            keys.extend(key);
+        }
+
+        let close = object.end_byte().saturating_sub(1);
+        if !self.text(object).ends_with('}') {
+            return;
+        }
+        for (name, value) in pattern_defaults(self.content, &pattern) {
+            if keys.contains(&name) {
+                continue;
+            }
+            let label = format!("{} = {}", name, value);
+            let mut hint = self.hint(close, label, Some(lsp::InlayHintKind::PARAMETER));
+            hint.padding_left = Some(true);
+            hint.padding_right = Some(true);
+            hint.data = Some(data.clone());
+            hints.push(hint);
+        }
+    }
+
+    /// Compute the index hints for the items of a long array literal.
+    fn array(&self, array: &tree_sitter::Node, hints: &mut Vec<lsp::InlayHint>) {
+        if !self.settings.array_indices {
+            return;
+        }
+        let items = (0 .. array.named_child_count())
+            .filter_map(|i| array.named_child(i))
+            .filter(|item| item.kind() != "comment")
+            .collect::<Vec<_>>();
+        if (items.len() as u32) < LONG_ARRAY {
+            return;
+        }
+        // NOTE: holes, e.g., `[a, , b]`, count towards the indices of the following items.
+        let mut index = 0;
+        let mut previous = None;
+        for i in 0 .. array.child_count() {
+            let child = match array.child(i) {
+                Some(child) => child,
+                None => continue,
+            };
+            match &*child.kind() {
+                "," => {
+                    if previous == Some(",") || previous == Some("[") {
+                        index += 1;
+                    }
+                    previous = Some(",");
+                },
+                "[" => previous = Some("["),
+                "]" | "comment" => {},
+                _ => {
+                    let mut hint = self.hint(child.start_byte(), format!("[{}]", index), None);
+                    hint.padding_right = Some(true);
+                    hints.push(hint);
+                    index += 1;
+                    previous = None;
+                },
+            }
+        }
+    }
+}
+
+fn is_literal(node: &tree_sitter::Node) -> bool {
+    match &*node.kind() {
+        "number" | "string" | "template_string" | "true" | "false" | "null" | "regex" => true,
+        "unary_expression" => node.named_child(0).map_or(false, |argument| argument.kind() == "number"),
+        _ => false,
+    }
+}
+
+/// The text of a property key, without quotes.
+fn property_key(content: &ropey::Rope, key: &tree_sitter::Node) -> String {
+    let text = content.utf8_text_for_tree_sitter_node(key).to_string();
+    if key.kind() == "string" && text.len() >= 2 {
+        text[1 .. text.len() - 1].to_string()
+    } else {
+        text
+    }
+}
+
+fn elide(text: &str) -> String {
+    let text = declaration::single_line(text);
+    if text.chars().count() > MAX_VALUE_LENGTH {
+        let prefix = text.chars().take(MAX_VALUE_LENGTH - 1).collect::<String>();
+        format!("{}…", prefix)
+    } else {
+        text
+    }
+}
+
+/// Describe the value a parameter takes when its argument is left out, e.g., `b = 1`, or
+/// `retries = 3, delay = 100` for a destructured parameter with a default object.
+fn default_value(content: &ropey::Rope, parameter: &tree_sitter::Node) -> Option<String> {
+    if parameter.kind() != "assignment_pattern" {
+        return None;
+    }
+    let left = parameter.child_by_field_name("left")?;
+    let right = parameter.child_by_field_name("right")?;
+    match &*left.kind() {
+        "identifier" => {
+            let name = content.utf8_text_for_tree_sitter_node(&left);
+            let value = content.utf8_text_for_tree_sitter_node(&right);
+            Some(format!("{} = {}", name, elide(&value)))
+        },
+        // NOTE: only an empty default object leaves the values to the destructured defaults.
+        "object_pattern" if right.kind() == "object" && right.named_child_count() == 0 => {
+            let defaults = pattern_defaults(content, &left);
+            let defaults = defaults
+                .into_iter()
+                .map(|(name, value)| format!("{} = {}", name, value))
+                .collect::<Vec<_>>();
+            Some(defaults.join(", ")).filter(|defaults| !defaults.is_empty())
+        },
+        _ => None,
+    }
+}
+
+/// Return the names and default values of the properties of an object pattern which have one.
+fn pattern_defaults(content: &ropey::Rope, pattern: &tree_sitter::Node) -> Vec<(String, String)> {
+    let text = |node: &tree_sitter::Node| content.utf8_text_for_tree_sitter_node(node).to_string();
+    let mut defaults = vec![];
+    for i in 0 .. pattern.named_child_count() {
+        let property = match pattern.named_child(i) {
+            Some(property) => property,
+            None => continue,
+        };
+        let default = match &*property.kind() {
+            "object_assignment_pattern" => property
+                .child_by_field_name("left")
+                .zip(property.child_by_field_name("right"))
+                .map(|(left, right)| (text(&left), elide(&text(&right)))),
+            "pair_pattern" => property
+                .child_by_field_name("key")
+                .zip(property.child_by_field_name("value"))
+                .filter(|(_, value)| value.kind() == "assignment_pattern")
+                .and_then(|(key, value)| {
+                    let right = value.child_by_field_name("right")?;
+                    Some((property_key(content, &key), elide(&text(&right))))
+                }),
+            _ => None,
+        };
+        defaults.extend(default);
+    }
+    defaults
+}
+
+#[cfg(test)]
+mod tests {
+    use super::elide;
+
+    #[test]
+    fn elide_long_values() {
+        assert_eq!(elide("{ a: 1 }"), "{ a: 1 }");
+        assert_eq!(elide("[\n    1,\n    2,\n]"), "[ 1, 2, ]");
+        assert_eq!(elide("\"a very long string literal value\""), "\"a very long string lit…");
+    }
+}
@@ -0,0 +1,126 @@
+use crate::provider::formatting;
+use lsp_text::RopeExt;
+
+pub fn on_type_formatting(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+    ch: &str,
+    options: &lsp::FormattingOptions,
+) -> anyhow::Result<Option<Vec<lsp::TextEdit>>> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+
+    let edits = match ch {
+        // NOTE: only the line of the brace is re-indented, to match the line which opened the block;
+        // the rest of the block is left as it is.
+        "}" => {
+            let token = match typed_token(tree, byte, ch) {
+                Some(token) => token,
+                None => return Ok(None),
+            };
+            let line_start = content.line_to_byte(token.start_position().row() as usize) as u32;
+            formatting::edits(content, tree, options, Some(line_start .. token.end_byte()))
+        },
+        // NOTE: only the lines of the statement which the semicolon completes are normalized.
+        ";" => {
+            let token = match typed_token(tree, byte, ch) {
+                Some(token) => token,
+                None => return Ok(None),
+            };
+            let statement = match token.parent() {
+                Some(statement) => statement,
+                None => return Ok(None),
+            };
+            let line_start = content.line_to_byte(statement.start_position().row() as usize) as u32;
+            formatting::edits(content, tree, options, Some(line_start .. token.end_byte()))
+        },
+        "\n" => new_line(content, tree, options, position.line as usize),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(edits))
+}
+
+/// Find the token which was just typed before the offset.
+fn typed_token<'tree>(tree: &'tree tree_sitter::Tree, byte: u32, ch: &str) -> Option<tree_sitter::Node<'tree>> {
+    let start = byte.checked_sub(ch.len() as u32)?;
+    let node = tree.root_node().descendant_for_byte_range(start, byte)?;
+    Some(node).filter(|node| !node.is_named() && node.kind() == ch && !node.is_missing())
+}
+
+/// Normalize the line which was just completed and indent the new line.
+fn new_line(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    options: &lsp::FormattingOptions,
+    row: usize,
+) -> Vec<lsp::TextEdit> {
+    let line_start = content.line_to_byte(row) as u32;
+    let line = content.line(row).to_string();
+    let line_end = line_start + line.trim_end_matches(&['\r', '\n'][..]).len() as u32;
+
+    let mut edits = vec![];
+    if row > 0 {
+        let previous_start = content.line_to_byte(row - 1) as u32;
+        edits.extend(formatting::edits(content, tree, options, Some(previous_start .. line_start - 1)));
+    }
+
+    if line.trim().is_empty() {
+        let indent = formatting::indent_at(content, tree, options, line_end);
+        let old = &line[.. (line_end - line_start) as usize];
+        if indent != old {
+            let start = lsp::Position::new(row as u32, 0);
+            let end = content.byte_to_lsp_position(line_end as usize);
+            edits.push(lsp::TextEdit::new(lsp::Range::new(start, end), indent));
+        }
+    } else {
+        edits.extend(formatting::edits(content, tree, options, Some(line_start .. line_end)));
+    }
+
+    edits
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::on_type_formatting;
+    use crate::core::test::{apply, parse};
+
+    /// Format after typing the character at the end of the first occurrence of `typed`.
+    fn on_type(source: &str, typed: &str, ch: &str) -> Option<String> {
+        let (content, tree) = parse(source);
+        let byte = source.find(typed).unwrap() + typed.len();
+        let position = lsp_text::RopeExt::byte_to_lsp_position(&content, byte);
+        let options = lsp::FormattingOptions {
+            tab_size: 2,
+            insert_spaces: true,
+            ..Default::default()
+        };
+        let edits = on_type_formatting(&content, &tree, position, ch, &options).unwrap()?;
+        Some(apply(&content, &edits))
+    }
+
+    #[test]
+    fn closing_brace_indents_only_its_line() {
+        let source = "if(a){\nb( )\n    }\n";
+        assert_eq!(on_type(source, "    }", "}").unwrap(), "if(a){\nb( )\n}\n");
+    }
+
+    #[test]
+    fn semicolon_formats_only_the_completed_statement() {
+        let source = "a( 1 );\nlet  x=[\n1,2];\nb( 2 );\n";
+        assert_eq!(on_type(source, "2];", ";").unwrap(), "a( 1 );\nlet x = [\n  1, 2];\nb( 2 );\n");
+    }
+
+    #[test]
+    fn new_line_formats_the_previous_line_and_indents() {
+        let source = "if (a) {\nb( )\n\n}\n";
+        assert_eq!(on_type(source, "b( )\n", "\n").unwrap(), "if (a) {\n  b()\n  \n}\n");
+    }
+
+    #[test]
+    fn ignores_other_characters() {
+        assert_eq!(on_type("a( 1 );\n", "a( 1 )", ")"), None);
+        // NOTE: the token before the position is not the typed character.
+        assert_eq!(on_type("a( 1 );\n", "a( 1 );", "}"), None);
+    }
+}
@@ -0,0 +1,1057 @@
+use crate::core::{
+    declaration,
+    scope::{BindingId, BindingKind, Occurrence, ScopeKind, Scopes},
+};
+use lsp_text::RopeExt;
+use std::ops::Range;
+
+/// The refactorings offered as code actions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Refactor {
+    ExtractConstant,
+    ExtractFunction,
+    InlineVariable,
+    ConvertVar,
+    ToArrowFunction,
+    ToFunctionExpression,
+    ToTemplateLiteral,
+    ToImport,
+}
+
+impl Refactor {
+    pub const ALL: &'static [Refactor] = &[
+        Refactor::ExtractConstant,
+        Refactor::ExtractFunction,
+        Refactor::InlineVariable,
+        Refactor::ConvertVar,
+        Refactor::ToArrowFunction,
+        Refactor::ToFunctionExpression,
+        Refactor::ToTemplateLiteral,
+        Refactor::ToImport,
+    ];
+
+    /// The identifier of the refactoring, used to resolve its code action.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Refactor::ExtractConstant => "extract-constant",
+            Refactor::ExtractFunction => "extract-function",
+            Refactor::InlineVariable => "inline-variable",
+            Refactor::ConvertVar => "convert-var",
+            Refactor::ToArrowFunction => "to-arrow-function",
+            Refactor::ToFunctionExpression => "to-function-expression",
+            Refactor::ToTemplateLiteral => "to-template-literal",
+            Refactor::ToImport => "to-import",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Refactor::ALL.iter().copied().find(|refactor| refactor.id() == id)
+    }
+
+    pub fn kind(&self) -> lsp::CodeActionKind {
+        match self {
+            Refactor::ExtractConstant | Refactor::ExtractFunction => lsp::CodeActionKind::REFACTOR_EXTRACT,
+            Refactor::InlineVariable => lsp::CodeActionKind::REFACTOR_INLINE,
+            _ => lsp::CodeActionKind::REFACTOR_REWRITE,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Refactor::ExtractConstant => "Extract to constant",
+            Refactor::ExtractFunction => "Extract to function",
+            Refactor::InlineVariable => "Inline variable",
+            Refactor::ConvertVar => "Convert `var` to `let` or `const`",
+            Refactor::ToArrowFunction => "Convert to arrow function",
+            Refactor::ToFunctionExpression => "Convert to function expression",
+            Refactor::ToTemplateLiteral => "Convert to template literal",
+            Refactor::ToImport => "Convert `require` to `import`",
+        }
+    }
+
+    /// Whether the refactoring applies to the selection.
+    pub fn applies(&self, context: &Context) -> bool {
+        match self {
+            Refactor::ExtractConstant => extract_constant_target(context).is_some(),
+            Refactor::ExtractFunction => extract_function_target(context).is_some(),
+            Refactor::InlineVariable => inline_variable_target(context).is_some(),
+            Refactor::ConvertVar => convert_var_target(context).is_some(),
+            Refactor::ToArrowFunction => to_arrow_function_target(context).is_some(),
+            Refactor::ToFunctionExpression => to_function_expression_target(context).is_some(),
+            Refactor::ToTemplateLiteral => to_template_literal_target(context).is_some(),
+            Refactor::ToImport => to_import_target(context).is_some(),
+        }
+    }
+
+    /// Compute the edits performing the refactoring, or [`None`] if it does not apply.
+    pub fn edits(&self, context: &Context) -> Option<Vec<lsp::TextEdit>> {
+        match self {
+            Refactor::ExtractConstant => extract_constant(context),
+            Refactor::ExtractFunction => extract_function(context),
+            Refactor::InlineVariable => inline_variable(context),
+            Refactor::ConvertVar => convert_var(context),
+            Refactor::ToArrowFunction => to_arrow_function(context),
+            Refactor::ToFunctionExpression => to_function_expression(context),
+            Refactor::ToTemplateLiteral => to_template_literal(context),
+            Refactor::ToImport => to_import(context),
+        }
+    }
+}
+
+/// The document and selection a refactoring is applied to.
+pub struct Context<'a> {
+    pub content: &'a ropey::Rope,
+    pub tree: &'a tree_sitter::Tree,
+    pub scopes: Scopes,
+    /// The selected bytes, without surrounding whitespace.
+    pub selection: Range<u32>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(content: &'a ropey::Rope, tree: &'a tree_sitter::Tree, range: lsp::Range) -> anyhow::Result<Self> {
+        let mut start = content.lsp_position_to_core(range.start)?.byte;
+        let mut end = content.lsp_position_to_core(range.end)?.byte;
+        while start < end && content.byte(start as usize).is_ascii_whitespace() {
+            start += 1;
+        }
+        while start < end && content.byte(end as usize - 1).is_ascii_whitespace() {
+            end -= 1;
+        }
+        let scopes = Scopes::analyze(content, tree);
+        Ok(Context {
+            content,
+            tree,
+            scopes,
+            selection: start .. end,
+        })
+    }
+
+    /// The smallest named node covering the selection.
+    fn node(&self) -> Option<tree_sitter::Node<'a>> {
+        let Range { start, end } = self.selection;
+        self.tree.root_node().named_descendant_for_byte_range(start, end)
+    }
+
+    fn text(&self, node: &tree_sitter::Node) -> String {
+        self.content.utf8_text_for_tree_sitter_node(node).to_string()
+    }
+
+    fn slice(&self, range: Range<u32>) -> String {
+        self.content.byte_slice(range.start as usize .. range.end as usize).to_string()
+    }
+
+    fn range(&self, range: Range<u32>) -> lsp::Range {
+        let start = self.content.byte_to_lsp_position(range.start as usize);
+        let end = self.content.byte_to_lsp_position(range.end as usize);
+        lsp::Range::new(start, end)
+    }
+
+    fn edit(&self, range: Range<u32>, new_text: impl Into<String>) -> lsp::TextEdit {
+        lsp::TextEdit::new(self.range(range), new_text.into())
+    }
+
+    /// The indentation of the line the node starts on.
+    fn indent_of(&self, node: &tree_sitter::Node) -> String {
+        let line = self.content.line(node.start_position().row() as usize);
+        line.chars().take_while(|&c| c == ' ' || c == '\t').collect()
+    }
+
+    /// The unit of indentation used by the document, e.g., four spaces.
+    fn indent_unit(&self) -> String {
+        let indents = self.content.lines().filter_map(|line| {
+            let indent = line.chars().take_while(|&c| c == ' ' || c == '\t').collect::<String>();
+            let is_blank = line.chars().all(char::is_whitespace);
+            Some(indent).filter(|indent| !indent.is_empty() && !is_blank)
+        });
+        let mut unit: Option<String> = None;
+        for indent in indents {
+            if indent.starts_with('\t') {
+                return "\t".into();
+            }
+            if unit.as_ref().map_or(true, |unit| indent.len() < unit.len()) {
+                unit = Some(indent);
+            }
+        }
+        unit.unwrap_or_else(|| "    ".into())
+    }
+
+    /// Return a name based on `base` which is not used anywhere in the document.
+    fn unique_name(&self, base: &str) -> String {
+        let is_used = |name: &str| {
+            let bindings = self.scopes.bindings.iter().map(|binding| &binding.name);
+            let references = self.scopes.references.iter().map(|reference| &reference.name);
+            bindings.chain(references).any(|other| other == name)
+        };
+        std::iter::once(base.to_string())
+            .chain((1 ..).map(|i| format!("{}{}", base, i)))
+            .find(|name| !is_used(name))
+            .unwrap_or_default()
+    }
+}
+
+/// Node kinds which hold a list of statements.
+fn is_statement_list(node: &tree_sitter::Node) -> bool {
+    matches!(&*node.kind(), "program" | "statement_block" | "switch_case" | "switch_default")
+}
+
+/// Whether a function of this kind has its own `this`, `arguments` and `super`.
+fn has_own_context(node: &tree_sitter::Node) -> bool {
+    (declaration::is_function(node) && node.kind() != "arrow_function") || node.kind() == "class_body"
+}
+
+/// Whether the node is an expression which can be moved into a variable.
+fn is_expression(node: &tree_sitter::Node) -> bool {
+    matches!(
+        &*node.kind(),
+        "array"
+            | "arrow_function"
+            | "await_expression"
+            | "binary_expression"
+            | "call_expression"
+            | "class"
+            | "false"
+            | "function"
+            | "function_expression"
+            | "identifier"
+            | "member_expression"
+            | "new_expression"
+            | "null"
+            | "number"
+            | "object"
+            | "parenthesized_expression"
+            | "regex"
+            | "string"
+            | "subscript_expression"
+            | "template_string"
+            | "ternary_expression"
+            | "true"
+            | "unary_expression"
+    )
+}
+
+/// Whether the node is an expression which never needs parentheses when substituted for an
+/// identifier.
+fn is_primary(node: &tree_sitter::Node) -> bool {
+    matches!(
+        &*node.kind(),
+        "array"
+            | "call_expression"
+            | "false"
+            | "identifier"
+            | "member_expression"
+            | "null"
+            | "number"
+            | "parenthesized_expression"
+            | "string"
+            | "subscript_expression"
+            | "template_string"
+            | "this"
+            | "true"
+    )
+}
+
+/// Whether any node in the subtree satisfies the predicate, without descending into nodes for
+/// which `stop` holds.
+fn any_descendant(
+    node: &tree_sitter::Node,
+    stop: &impl Fn(&tree_sitter::Node) -> bool,
+    predicate: &impl Fn(&tree_sitter::Node) -> bool,
+) -> bool {
+    (0 .. node.child_count()).filter_map(|i| node.child(i)).any(|child| {
+        predicate(&child) || (!stop(&child) && any_descendant(&child, stop, predicate))
+    })
+}
+
+/// Whether the code uses `this`, `arguments`, `super` or `new.target` of its enclosing function.
+fn uses_function_context(context: &Context, node: &tree_sitter::Node) -> bool {
+    let is_context = |node: &tree_sitter::Node| match &*node.kind() {
+        "this" | "super" | "meta_property" => true,
+        "identifier" => context.text(node) == "arguments",
+        _ => false,
+    };
+    // NOTE: the code may be a single node, e.g., the expression body of `() => this`.
+    is_context(node) || any_descendant(node, &has_own_context, &is_context)
+}
+
+/// Return the statement, directly within a statement list, which contains the node. Returns
+/// [`None`] if a function or class lies in between.
+fn enclosing_statement<'tree>(node: &tree_sitter::Node<'tree>) -> Option<tree_sitter::Node<'tree>> {
+    let mut current = node.clone();
+    loop {
+        let parent = current.parent()?;
+        if is_statement_list(&parent) {
+            return Some(current);
+        }
+        if declaration::is_function(&parent) || parent.kind() == "class_body" {
+            return None;
+        }
+        current = parent;
+    }
+}
+
+/// The range of the statement including its line, if nothing else is on the line.
+fn statement_lines(context: &Context, statement: &tree_sitter::Node) -> Range<u32> {
+    let content = context.content;
+    let row = statement.start_position().row() as usize;
+    let line_start = content.line_to_byte(row) as u32;
+    let before = context.slice(line_start .. statement.start_byte());
+
+    let end_row = statement.end_position().row() as usize;
+    let next_line_start = if end_row + 1 < content.len_lines() {
+        content.line_to_byte(end_row + 1) as u32
+    } else {
+        content.len_bytes() as u32
+    };
+    let after = context.slice(statement.end_byte() .. next_line_start);
+
+    if before.trim().is_empty() && after.trim().is_empty() {
+        line_start .. next_line_start
+    } else {
+        statement.start_byte() .. statement.end_byte()
+    }
+}
+
+// Extract to constant
+
+fn extract_constant_target<'a>(context: &Context<'a>) -> Option<(tree_sitter::Node<'a>, tree_sitter::Node<'a>)> {
+    if context.selection.is_empty() {
+        return None;
+    }
+    let node = context.node()?;
+    if node.start_byte() != context.selection.start || node.end_byte() != context.selection.end {
+        return None;
+    }
+    if !is_expression(&node) {
+        return None;
+    }
+    if let Some(Occurrence::Binding(_)) = context.scopes.occurrence_at(node.start_byte()) {
+        return None;
+    }
+    // NOTE: assignment targets cannot be replaced by a constant.
+    let parent = node.parent()?;
+    let is_target = match &*parent.kind() {
+        "assignment_expression" | "augmented_assignment_expression" => {
+            parent.child_by_field_name("left").as_ref() == Some(&node)
+        },
+        "update_expression" => true,
+        _ => false,
+    };
+    if is_target {
+        return None;
+    }
+    let statement = enclosing_statement(&node)?;
+    // NOTE: the constant is declared before the statement, where names it declares are not yet visible.
+    let scopes = &context.scopes;
+    let is_declared_by_statement = scopes.references.iter().any(|reference| {
+        let is_inside = node.start_byte() <= reference.span.start && reference.span.end <= node.end_byte();
+        let binding = reference.binding.map(|binding| &scopes.bindings[binding]);
+        is_inside
+            && binding.map_or(false, |binding| {
+                statement.start_byte() <= binding.span.start && binding.span.end <= statement.end_byte()
+            })
+    });
+    if is_declared_by_statement {
+        return None;
+    }
+    Some((node, statement))
+}
+
+fn extract_constant(context: &Context) -> Option<Vec<lsp::TextEdit>> {
+    let (expression, statement) = extract_constant_target(context)?;
+    let name = context.unique_name("extracted");
+    let indent = context.indent_of(&statement);
+
+    // NOTE: a single edit from the start of the statement to the end of the expression, since
+    // the expression may start where the declaration is inserted.
+    let new_text = format!(
+        "const {} = {};\n{}{}{}",
+        name,
+        context.text(&expression),
+        indent,
+        context.slice(statement.start_byte() .. expression.start_byte()),
+        name
+    );
+    Some(vec![context.edit(statement.start_byte() .. expression.end_byte(), new_text)])
+}
+
+// Extract to function
+
+struct ExtractFunction<'a> {
+    statements: Vec<tree_sitter::Node<'a>>,
+    parameters: Vec<String>,
+    /// The binding declared by the statements which is used after them, if any.
+    result: Option<BindingId>,
+}
+
+fn extract_function_target<'a>(context: &Context<'a>) -> Option<ExtractFunction<'a>> {
+    if context.selection.is_empty() {
+        return None;
+    }
+
+    let mut container = context.node()?;
+    while !is_statement_list(&container) {
+        let parent = container.parent()?;
+        if declaration::is_function(&parent) || parent.kind() == "class_body" {
+            return None;
+        }
+        container = parent;
+    }
+
+    // NOTE: the selection may omit the semicolon of the last statement.
+    let Range { start, end } = context.selection;
+    let statements = (0 .. container.named_child_count())
+        .filter_map(|i| container.named_child(i))
+        .filter(|statement| statement.start_byte() < end && start < statement.end_byte())
+        .collect::<Vec<_>>();
+    let first = statements.first()?;
+    let last = statements.last()?;
+    let ends_with_semicolon = context.slice(end .. last.end_byte()) == ";";
+    if first.start_byte() != start || (last.end_byte() != end && !ends_with_semicolon) {
+        return None;
+    }
+
+    // NOTE: control flow which leaves the statements cannot be moved into a function.
+    let leaves = statements.iter().any(|statement| {
+        let is_leaving = |node: &tree_sitter::Node| {
+            matches!(
+                &*node.kind(),
+                "return_statement" | "break_statement" | "continue_statement" | "yield_expression" | "await_expression"
+            )
+        };
+        is_leaving(statement) || any_descendant(statement, &declaration::is_function, &is_leaving)
+    });
+    if leaves || statements.iter().any(|statement| uses_function_context(context, statement)) {
+        return None;
+    }
+
+    let scopes = &context.scopes;
+    let inside = |span: &Range<u32>| span.start >= start && span.end <= last.end_byte();
+
+    let mut parameters: Vec<String> = vec![];
+    for reference in scopes.references.iter().filter(|reference| inside(&reference.span)) {
+        let binding = match reference.binding {
+            Some(binding) => &scopes.bindings[binding],
+            None => continue,
+        };
+        // NOTE: module bindings remain visible from the extracted function.
+        if inside(&binding.span) || scopes.scopes[binding.scope].kind == ScopeKind::Module {
+            continue;
+        }
+        // NOTE: assignments to a parameter would not be visible to the caller.
+        if reference.write {
+            return None;
+        }
+        if !parameters.contains(&binding.name) {
+            parameters.push(binding.name.clone());
+        }
+    }
+
+    let mut results = scopes.bindings.iter().enumerate().filter(|(_, binding)| {
+        inside(&binding.span)
+            && binding.references.iter().any(|&reference| !inside(&scopes.references[reference].span))
+    });
+    let result = match (results.next(), results.next()) {
+        (None, _) => None,
+        (Some((id, binding)), None) => {
+            // NOTE: only simple declarations, e.g., `let x = ...`, can become `let x = extracted()`.
+            if !matches!(binding.kind, BindingKind::Var | BindingKind::Let | BindingKind::Const) {
+                return None;
+            }
+            let declarator = declaration::node(context.tree, binding)?;
+            let name = declarator.child_by_field_name("name")?;
+            if name.kind() != "identifier" || !statements.contains(&declarator.parent()?) {
+                return None;
+            }
+            Some(id)
+        },
+        _ => return None,
+    };
+
+    Some(ExtractFunction {
+        statements,
+        parameters,
+        result,
+    })
+}
+
+fn extract_function(context: &Context) -> Option<Vec<lsp::TextEdit>> {
+    let target = extract_function_target(context)?;
+    let first = target.statements.first()?;
+    let last = target.statements.last()?;
+    let name = context.unique_name("extracted");
+    let parameters = target.parameters.join(", ");
+    let unit = context.indent_unit();
+
+    // NOTE: the body is reindented relative to the line of the first statement.
+    let base = context.indent_of(first);
+    let body = context.slice(first.start_byte() .. last.end_byte());
+    let mut lines = body
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = if i == 0 { line } else { line.strip_prefix(&base).unwrap_or(line) };
+            if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", unit, line)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let call = format!("{}({})", name, parameters);
+    let call = match target.result {
+        Some(result) => {
+            let binding = &context.scopes.bindings[result];
+            lines.push(format!("{}return {};", unit, binding.name));
+            let keyword = binding.kind.keyword().unwrap_or("let");
+            format!("{} {} = {};", keyword, binding.name, call)
+        },
+        None => format!("{};", call),
+    };
+    let function = format!("function {}({}) {{\n{}\n}}", name, parameters, lines.join("\n"));
+
+    // NOTE: the function is declared at the top level, after the statement containing the
+    // selection.
+    let top = std::iter::successors(Some(first.clone()), |node| node.parent())
+        .find(|node| node.parent().map_or(false, |parent| parent.kind() == "program"))?;
+    let edits = if top == *first {
+        let new_text = format!("{}\n\n{}", call, function);
+        vec![context.edit(first.start_byte() .. last.end_byte(), new_text)]
+    } else {
+        vec![
+            context.edit(first.start_byte() .. last.end_byte(), call),
+            context.edit(top.end_byte() .. top.end_byte(), format!("\n\n{}", function)),
+        ]
+    };
+    Some(edits)
+}
+
+// Inline variable
+
+struct InlineVariable<'a> {
+    binding: BindingId,
+    statement: tree_sitter::Node<'a>,
+    value: tree_sitter::Node<'a>,
+}
+
+fn inline_variable_target<'a>(context: &Context<'a>) -> Option<InlineVariable<'a>> {
+    let scopes = &context.scopes;
+    let id = scopes.binding_at(context.selection.start)?;
+    let binding = &scopes.bindings[id];
+    if !matches!(binding.kind, BindingKind::Var | BindingKind::Let | BindingKind::Const) {
+        return None;
+    }
+
+    let declarator = declaration::node(context.tree, binding)?;
+    if declarator.kind() != "variable_declarator" || declarator.child_by_field_name("name")?.kind() != "identifier" {
+        return None;
+    }
+    let value = declarator.child_by_field_name("value")?;
+    let statement = declarator.parent()?;
+    let is_single = (0 .. statement.named_child_count())
+        .filter_map(|i| statement.named_child(i))
+        .filter(|child| child.kind() == "variable_declarator")
+        .count()
+        == 1;
+    if !is_single || !statement.parent().map_or(false, |parent| is_statement_list(&parent)) {
+        return None;
+    }
+
+    let references = &binding.references;
+    if references.iter().any(|&reference| scopes.references[reference].write) {
+        return None;
+    }
+    // NOTE: duplicating an expression with side effects (or identity) would change its meaning.
+    let is_pure = matches!(
+        &*value.kind(),
+        "number" | "string" | "true" | "false" | "null" | "identifier" | "this"
+    );
+    if references.len() > 1 && !is_pure {
+        return None;
+    }
+
+    // NOTE: the names used by the value must refer to the same bindings where it is inlined.
+    let root = context.tree.root_node();
+    let value_span = value.start_byte() .. value.end_byte();
+    let captured = scopes
+        .references
+        .iter()
+        .filter(|reference| value_span.start <= reference.span.start && reference.span.end <= value_span.end)
+        .collect::<Vec<_>>();
+    for &reference in references {
+        let site = &scopes.references[reference];
+        let node = root.named_descendant_for_byte_range(site.span.start, site.span.end)?;
+        if node.parent().map_or(false, |parent| parent.kind() == "export_specifier") {
+            return None;
+        }
+        let is_shadowed = captured
+            .iter()
+            .any(|captured| scopes.lookup(site.scope, &captured.name) != captured.binding);
+        if is_shadowed {
+            return None;
+        }
+    }
+
+    Some(InlineVariable {
+        binding: id,
+        statement,
+        value,
+    })
+}
+
+fn inline_variable(context: &Context) -> Option<Vec<lsp::TextEdit>> {
+    let target = inline_variable_target(context)?;
+    let scopes = &context.scopes;
+    let root = context.tree.root_node();
+    let value = context.text(&target.value);
+
+    let mut edits = vec![context.edit(statement_lines(context, &target.statement), "")];
+    for &reference in &scopes.bindings[target.binding].references {
+        let span = scopes.references[reference].span.clone();
+        let node = root.named_descendant_for_byte_range(span.start, span.end)?;
+        let parent = node.parent();
+        let needs_parentheses = !is_primary(&target.value)
+            && !parent.map_or(false, |parent| {
+                matches!(
+                    &*parent.kind(),
+                    "arguments"
+                        | "array"
+                        | "expression_statement"
+                        | "pair"
+                        | "parenthesized_expression"
+                        | "return_statement"
+                        | "template_substitution"
+                        | "variable_declarator"
+                ) || (parent.kind() == "assignment_expression"
+                    && parent.child_by_field_name("right").as_ref() == Some(&node))
+            });
+        let value = if needs_parentheses {
+            format!("({})", value)
+        } else {
+            value.clone()
+        };
+        let new_text = if node.kind() == "shorthand_property_identifier" {
+            format!("{}: {}", context.text(&node), value)
+        } else {
+            value
+        };
+        edits.push(context.edit(span, new_text));
+    }
+    Some(edits)
+}
+
+// Convert `var` to `let` or `const`
+
+fn convert_var_target<'a>(context: &Context<'a>) -> Option<(tree_sitter::Node<'a>, &'static str)> {
+    let declaration = std::iter::successors(context.node(), |node| node.parent())
+        .take_while(|node| !is_statement_list(node) && !declaration::is_function(node))
+        .find(|node| node.kind() == "variable_declaration")?;
+    let scopes = &context.scopes;
+    let root = context.tree.root_node();
+
+    // NOTE: `let` and `const` are block scoped, so every use must follow the declaration within
+    // the enclosing block (or `for` statement).
+    let container = declaration.parent()?;
+    let visible = declaration.start_byte() .. container.end_byte();
+
+    let mut is_const = true;
+    for i in 0 .. declaration.named_child_count() {
+        let declarator = match declaration.named_child(i) {
+            Some(declarator) if declarator.kind() == "variable_declarator" => declarator,
+            _ => continue,
+        };
+        is_const &= declarator.child_by_field_name("value").is_some();
+        let span = declarator.start_byte() .. declarator.end_byte();
+        for binding in scopes.bindings.iter().filter(|binding| binding.declaration == span) {
+            for &reference in &binding.references {
+                let reference = &scopes.references[reference];
+                if reference.span.start < visible.start || visible.end < reference.span.end {
+                    return None;
+                }
+                // NOTE: `let` and `const` cannot be redeclared.
+                let node = root.named_descendant_for_byte_range(reference.span.start, reference.span.end)?;
+                if node.parent().map_or(false, |parent| parent.kind() == "variable_declarator") {
+                    return None;
+                }
+                is_const &= !reference.write;
+            }
+        }
+    }
+
+    is_const &= container.kind() != "for_statement";
+    Some((declaration, if is_const { "const" } else { "let" }))
+}
+
+fn convert_var(context: &Context) -> Option<Vec<lsp::TextEdit>> {
+    let (declaration, keyword) = convert_var_target(context)?;
+    let var = declaration.child(0).filter(|child| child.kind() == "var")?;
+    Some(vec![context.edit(var.start_byte() .. var.end_byte(), keyword)])
+}
+
+// Convert between function expressions and arrow functions
+
+/// Return the innermost function around the selection, if it is of the given kinds.
+fn enclosing_function<'a>(context: &Context<'a>, kinds: &[&str]) -> Option<tree_sitter::Node<'a>> {
+    std::iter::successors(context.node(), |node| node.parent())
+        .find(|node| declaration::is_function(node))
+        .filter(|function| kinds.contains(&&*function.kind()))
+}
+
+fn is_async(function: &tree_sitter::Node) -> bool {
+    (0 .. function.child_count())
+        .filter_map(|i| function.child(i))
+        .any(|child| child.kind() == "async")
+}
+
+fn to_arrow_function_target<'a>(context: &Context<'a>) -> Option<tree_sitter::Node<'a>> {
+    // NOTE: generators have no arrow form.
+    let function = enclosing_function(context, &["function", "function_expression"])?;
+    let body = function.child_by_field_name("body")?;
+    if uses_function_context(context, &body) {
+        return None;
+    }
+    // NOTE: the name of a function expression is only visible within it, e.g., for recursion.
+    let name = function.child_by_field_name("name");
+    if let Some(binding) = name.and_then(|name| context.scopes.binding_at(name.start_byte())) {
+        if !context.scopes.bindings[binding].references.is_empty() {
+            return None;
+        }
+    }
+    Some(function)
+}
+
+fn to_arrow_function(context: &Context) -> Option<Vec<lsp::TextEdit>> {
+    let function = to_arrow_function_target(context)?;
+    let parameters = function.child_by_field_name("parameters")?;
+    let body = function.child_by_field_name("body")?;
+
+    // NOTE: a body which only returns an expression becomes an expression body, parenthesized
+    // where it would otherwise be read as a block or end the arrow function early.
+    let statements = (0 .. body.named_child_count())
+        .filter_map(|i| body.named_child(i))
+        .collect::<Vec<_>>();
+    let body = match statements.as_slice() {
+        [statement] if statement.kind() == "return_statement" => match statement.named_child(0) {
+            Some(value) if matches!(&*value.kind(), "object" | "sequence_expression") => {
+                format!("({})", context.text(&value))
+            },
+            Some(value) => context.text(&value),
+            None => context.text(&body),
+        },
+        _ => context.text(&body),
+    };
+
+    let prefix = if is_async(&function) { "async " } else { "" };
+    let arrow = format!("{}{} => {}", prefix, context.text(&parameters), body);
+    let is_operand = function.parent().map_or(false, |parent| {
+        !matches!(
+            &*parent.kind(),
+            "arguments"
+                | "array"
+                | "assignment_expression"
+                | "pair"
+                | "parenthesized_expression"
+                | "return_statement"
+                | "variable_declarator"
+        )
+    });
+    let arrow = if is_operand { format!("({})", arrow) } else { arrow };
+    Some(vec![context.edit(function.start_byte() .. function.end_byte(), arrow)])
+}
+
+fn to_function_expression_target<'a>(context: &Context<'a>) -> Option<tree_sitter::Node<'a>> {
+    let function = enclosing_function(context, &["arrow_function"])?;
+    let body = function.child_by_field_name("body")?;
+    if uses_function_context(context, &body) {
+        return None;
+    }
+    Some(function)
+}
+
+fn to_function_expression(context: &Context) -> Option<Vec<lsp::TextEdit>> {
+    let function = to_function_expression_target(context)?;
+    let parameters = match function.child_by_field_name("parameters") {
+        Some(parameters) => context.text(&parameters),
+        None => format!("({})", context.text(&function.child_by_field_name("parameter")?)),
+    };
+    let body = function.child_by_field_name("body")?;
+    let body = if body.kind() == "statement_block" {
+        context.text(&body)
+    } else {
+        format!("{{ return {}; }}", context.text(&body))
+    };
+
+    let prefix = if is_async(&function) { "async " } else { "" };
+    let expression = format!("{}function {} {}", prefix, parameters, body);
+    // NOTE: a statement starting with `function` is a declaration.
+    let is_statement = function.parent().map_or(false, |parent| parent.kind() == "expression_statement");
+    let expression = if is_statement {
+        format!("({})", expression)
+    } else {
+        expression
+    };
+    Some(vec![context.edit(function.start_byte() .. function.end_byte(), expression)])
+}
+
+// Convert string concatenation to a template literal
+
+fn is_concatenation(context: &Context, node: &tree_sitter::Node) -> bool {
+    node.kind() == "binary_expression"
+        && node
+            .child_by_field_name("operator")
+            .map_or(false, |operator| context.text(&operator) == "+")
+}
+
+/// Flatten a left-associative chain of `+` into its operands.
+fn operands<'a>(context: &Context, node: tree_sitter::Node<'a>, operands: &mut Vec<tree_sitter::Node<'a>>) {
+    match (node.child_by_field_name("left"), node.child_by_field_name("right")) {
+        (Some(left), Some(right)) if is_concatenation(context, &node) => {
+            self::operands(context, left, operands);
+            operands.push(right);
+        },
+        _ => operands.push(node),
+    }
+}
+
+fn to_template_literal_target<'a>(
+    context: &Context<'a>,
+) -> Option<(tree_sitter::Node<'a>, Vec<tree_sitter::Node<'a>>)> {
+    let mut node = std::iter::successors(context.node(), |node| node.parent())
+        .take_while(|node| !is_statement_list(node))
+        .find(|node| is_concatenation(context, node))?;
+    while let Some(parent) = node.parent().filter(|parent| is_concatenation(context, parent)) {
+        node = parent;
+    }
+
+    let mut parts = vec![];
+    operands(context, node.clone(), &mut parts);
+    // NOTE: `1 + 2 + "a"` is `"3a"`, so the concatenation must be of strings from the start.
+    let first_string = parts.iter().position(|part| part.kind() == "string")?;
+    if first_string > 1 {
+        return None;
+    }
+    Some((node, parts))
+}
+
+fn to_template_literal(context: &Context) -> Option<Vec<lsp::TextEdit>> {
+    let (node, parts) = to_template_literal_target(context)?;
+    let mut template = String::from("`");
+    for part in parts {
+        let text = context.text(&part);
+        match &*part.kind() {
+            "string" => {
+                let quote = text.chars().next()?;
+                let inner = &text[1 .. text.len() - 1];
+                let inner = inner
+                    .replace(&format!("\\{}", quote), &quote.to_string())
+                    .replace('`', "\\`")
+                    .replace("${", "\\${");
+                template.push_str(&inner);
+            },
+            "template_string" => template.push_str(&text[1 .. text.len() - 1]),
+            _ => template.push_str(&format!("${{{}}}", text)),
+        }
+    }
+    template.push('`');
+    Some(vec![context.edit(node.start_byte() .. node.end_byte(), template)])
+}
+
+// Convert `require` to `import`
+
+fn to_import_target<'a>(context: &Context<'a>) -> Option<(tree_sitter::Node<'a>, String)> {
+    let statement = std::iter::successors(context.node(), |node| node.parent())
+        .find(|node| matches!(&*node.kind(), "lexical_declaration" | "variable_declaration"))?;
+    if statement.parent()?.kind() != "program" || statement.named_child_count() != 1 {
+        return None;
+    }
+    let declarator = statement.named_child(0)?;
+    let call = declarator.child_by_field_name("value")?;
+    let function = call.child_by_field_name("function")?;
+    if call.kind() != "call_expression" || function.kind() != "identifier" || context.text(&function) != "require" {
+        return None;
+    }
+    // NOTE: a local `require` is not the CommonJS one.
+    if context.scopes.binding_at(function.start_byte()).is_some() {
+        return None;
+    }
+    let arguments = call.child_by_field_name("arguments")?;
+    let source = arguments.named_child(0).filter(|source| source.kind() == "string")?;
+    if arguments.named_child_count() != 1 {
+        return None;
+    }
+    let source = context.text(&source);
+
+    let name = declarator.child_by_field_name("name")?;
+    let clause = match &*name.kind() {
+        "identifier" => context.text(&name),
+        "object_pattern" => {
+            let mut specifiers = vec![];
+            for i in 0 .. name.named_child_count() {
+                let property = name.named_child(i)?;
+                match &*property.kind() {
+                    "shorthand_property_identifier_pattern" | "shorthand_property_identifier" => {
+                        specifiers.push(context.text(&property));
+                    },
+                    "pair_pattern" => {
+                        let key = property.child_by_field_name("key")?;
+                        let value = property.child_by_field_name("value")?;
+                        if key.kind() != "property_identifier" || value.kind() != "identifier" {
+                            return None;
+                        }
+                        let (key, value) = (context.text(&key), context.text(&value));
+                        if key == value {
+                            specifiers.push(key);
+                        } else {
+                            specifiers.push(format!("{} as {}", key, value));
+                        }
+                    },
+                    _ => return None,
+                }
+            }
+            format!("{{ {} }}", specifiers.join(", "))
+        },
+        _ => return None,
+    };
+
+    Some((statement, format!("import {} from {};", clause, source)))
+}
+
+fn to_import(context: &Context) -> Option<Vec<lsp::TextEdit>> {
+    let (statement, import) = to_import_target(context)?;
+    Some(vec![context.edit(statement.start_byte() .. statement.end_byte(), import)])
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::{Context, Refactor};
+    use crate::core::test::{apply, parse};
+    use lsp_text::RopeExt;
+
+    /// Apply the refactoring to the source, where the selection is marked by `«` and `»`. Returns
+    /// [`None`] if the refactoring does not apply.
+    fn refactor(refactor: Refactor, source: &str) -> Option<String> {
+        let start = source.find('«').unwrap();
+        let end = source.find('»').unwrap() - '«'.len_utf8();
+        let source = source.replace(['«', '»'], "");
+        let (content, tree) = parse(&source);
+        let range = lsp::Range::new(content.byte_to_lsp_position(start), content.byte_to_lsp_position(end));
+        let context = Context::new(&content, &tree, range).unwrap();
+        let edits = refactor.edits(&context);
+        assert_eq!(refactor.applies(&context), edits.is_some());
+        edits.map(|edits| apply(&content, &edits))
+    }
+
+    #[test]
+    fn extract_constant() {
+        let source = "function f() {\n  return g(«1 + 2») * 3;\n}\n";
+        let expected = "function f() {\n  const extracted = 1 + 2;\n  return g(extracted) * 3;\n}\n";
+        assert_eq!(refactor(Refactor::ExtractConstant, source).unwrap(), expected);
+
+        assert_eq!(refactor(Refactor::ExtractConstant, "let a;\n«a» = 1;\n"), None);
+        assert_eq!(refactor(Refactor::ExtractConstant, "let a = «1 +» 2;\n"), None);
+        assert_eq!(refactor(Refactor::ExtractConstant, "for (let i = 0; «i» < 1; i++) {}\n"), None);
+    }
+
+    #[test]
+    fn extract_function() {
+        let source = "function f(a) {\n  «let b = a + 1;\n  g(b);»\n  return b;\n}\n";
+        let expected = concat!(
+            "function f(a) {\n  let b = extracted(a);\n  return b;\n}\n\n",
+            "function extracted(a) {\n  let b = a + 1;\n  g(b);\n  return b;\n}\n",
+        );
+        assert_eq!(refactor(Refactor::ExtractFunction, source).unwrap(), expected);
+    }
+
+    #[test]
+    fn extract_function_rejects_outer_writes_and_context() {
+        // NOTE: the assignment would only change the parameter of the extracted function.
+        assert_eq!(refactor(Refactor::ExtractFunction, "function f(a) {\n  «a = 1;»\n  g(a);\n}\n"), None);
+        // NOTE: only one binding declared by the statements can be returned.
+        let source = "function f() {\n  «let a = 1;\n  let b = 2;»\n  g(a, b);\n}\n";
+        assert_eq!(refactor(Refactor::ExtractFunction, source), None);
+        assert_eq!(refactor(Refactor::ExtractFunction, "function f() {\n  «g(this);»\n}\n"), None);
+        assert_eq!(refactor(Refactor::ExtractFunction, "function f() {\n  «return 1;»\n}\n"), None);
+    }
+
+    #[test]
+    fn inline_variable() {
+        let source = "let «a» = 1;\nf(a, { a });\n";
+        assert_eq!(refactor(Refactor::InlineVariable, source).unwrap(), "f(1, { a: 1 });\n");
+        let source = "let a = b + c;\nf(«a» * 2);\n";
+        assert_eq!(refactor(Refactor::InlineVariable, source).unwrap(), "f((b + c) * 2);\n");
+
+        assert_eq!(refactor(Refactor::InlineVariable, "let «a» = g();\nf(a, a);\n"), None);
+        assert_eq!(refactor(Refactor::InlineVariable, "let «a» = 1;\na = 2;\n"), None);
+        assert_eq!(refactor(Refactor::InlineVariable, "let «a» = b;\n{ let b; f(a); }\n"), None);
+    }
+
+    #[test]
+    fn convert_var() {
+        assert_eq!(refactor(Refactor::ConvertVar, "«var» a = 1;\nf(a);\n").unwrap(), "const a = 1;\nf(a);\n");
+        assert_eq!(refactor(Refactor::ConvertVar, "«var» a = 1;\na++;\n").unwrap(), "let a = 1;\na++;\n");
+        assert_eq!(refactor(Refactor::ConvertVar, "«var» a;\n").unwrap(), "let a;\n");
+
+        // NOTE: `let` and `const` are not hoisted, so uses before the declaration or outside of its
+        // block would no longer refer to it.
+        assert_eq!(refactor(Refactor::ConvertVar, "f(a);\n«var» a = 1;\n"), None);
+        assert_eq!(refactor(Refactor::ConvertVar, "{ «var» a = 1; }\nf(a);\n"), None);
+        assert_eq!(refactor(Refactor::ConvertVar, "«var» a = 1;\nvar a = 2;\n"), None);
+    }
+
+    #[test]
+    fn to_arrow_function() {
+        let source = "let f = «function» (a) { return a + 1; };\n";
+        assert_eq!(refactor(Refactor::ToArrowFunction, source).unwrap(), "let f = (a) => a + 1;\n");
+        let source = "let f = async «function» () { return { a }; };\n";
+        assert_eq!(refactor(Refactor::ToArrowFunction, source).unwrap(), "let f = async () => ({ a });\n");
+        let source = "let f = «function» () { return a, b; };\n";
+        assert_eq!(refactor(Refactor::ToArrowFunction, source).unwrap(), "let f = () => (a, b);\n");
+        let source = "let f = «function» () { g(); };\n";
+        assert_eq!(refactor(Refactor::ToArrowFunction, source).unwrap(), "let f = () => { g(); };\n");
+        let source = "«function» () {}.call();\n";
+        assert_eq!(refactor(Refactor::ToArrowFunction, source).unwrap(), "(() => {}).call();\n");
+    }
+
+    #[test]
+    fn to_arrow_function_rejects_function_context() {
+        assert_eq!(refactor(Refactor::ToArrowFunction, "let f = «function» () { return this; };\n"), None);
+        assert_eq!(refactor(Refactor::ToArrowFunction, "let f = «function» () { g(arguments); };\n"), None);
+        assert_eq!(refactor(Refactor::ToArrowFunction, "let f = «function» g() { g(); };\n"), None);
+        assert_eq!(refactor(Refactor::ToArrowFunction, "let f = «function»* () {};\n"), None);
+    }
+
+    #[test]
+    fn to_function_expression() {
+        let source = "let f = «a» => a + 1;\n";
+        let expected = "let f = function (a) { return a + 1; };\n";
+        assert_eq!(refactor(Refactor::ToFunctionExpression, source).unwrap(), expected);
+        let source = "«async» (a, b) => { g(); };\n";
+        assert_eq!(refactor(Refactor::ToFunctionExpression, source).unwrap(), "(async function (a, b) { g(); });\n");
+
+        assert_eq!(refactor(Refactor::ToFunctionExpression, "let f = «()» => this;\n"), None);
+        assert_eq!(refactor(Refactor::ToFunctionExpression, "let f = «()» => arguments;\n"), None);
+    }
+
+    #[test]
+    fn to_template_literal() {
+        let source = "let s = «\"a`\"» + b + 'c\\'' + `${d}`;\n";
+        assert_eq!(refactor(Refactor::ToTemplateLiteral, source).unwrap(), "let s = `a\\`${b}c'${d}`;\n");
+        let source = "let s = a + «\"b\"»;\n";
+        assert_eq!(refactor(Refactor::ToTemplateLiteral, source).unwrap(), "let s = `${a}b`;\n");
+
+        assert_eq!(refactor(Refactor::ToTemplateLiteral, "let s = 1 + 2 + «\"a\"»;\n"), None);
+        assert_eq!(refactor(Refactor::ToTemplateLiteral, "let s = «a» - \"b\";\n"), None);
+    }
+
+    #[test]
+    fn to_import() {
+        let source = "const «a» = require('a');\n";
+        assert_eq!(refactor(Refactor::ToImport, source).unwrap(), "import a from 'a';\n");
+        let source = "const { «a», b: c, d: d } = require('m');\n";
+        assert_eq!(refactor(Refactor::ToImport, source).unwrap(), "import { a, b as c, d } from 'm';\n");
+
+        assert_eq!(refactor(Refactor::ToImport, "function require() {}\nconst «a» = require('a');\n"), None);
+        assert_eq!(refactor(Refactor::ToImport, "const «a» = require(b);\n"), None);
+        assert_eq!(refactor(Refactor::ToImport, "{ const «a» = require('a'); }\n"), None);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::core::scope::{Occurrence, Scopes};
+use lsp_text::RopeExt;
+
+/// Return the locations of every occurrence of the name at the given position.
+pub fn references(
+    uri: &lsp::Url,
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+    include_declaration: bool,
+) -> anyhow::Result<Option<Vec<lsp::Location>>> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+    let scopes = Scopes::analyze(content, tree);
+    let occurrence = match scopes.occurrence_at(byte) {
+        Some(occurrence) => occurrence,
+        None => return Ok(None),
+    };
+    let locations = scopes
+        .related(occurrence)
+        .into_iter()
+        .filter(|occurrence| include_declaration || !matches!(occurrence, Occurrence::Binding(_)))
+        .map(|occurrence| lsp::Location::new(uri.clone(), scopes.range_of(occurrence)))
+        .collect();
+    Ok(Some(locations))
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::references;
+    use crate::core::test::{parse, position};
+
+    #[test]
+    fn finds_the_binding_and_its_references() {
+        let uri = lsp::Url::parse("file:///a.js").unwrap();
+        let (content, tree) = parse("let a = 1;\n{ let a = 2; }\nf(a, a);\n");
+        let starts = |include_declaration| {
+            references(&uri, &content, &tree, position(&content, "a, a"), include_declaration)
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect::<Vec<_>>()
+        };
+
+        let a = lsp::Position::new(0, 4);
+        let uses = [lsp::Position::new(2, 2), lsp::Position::new(2, 5)];
+        assert_eq!(starts(true), [a, uses[0], uses[1]]);
+        assert_eq!(starts(false), uses);
+    }
+
+    #[test]
+    fn finds_unresolved_names() {
+        let uri = lsp::Url::parse("file:///a.js").unwrap();
+        let (content, tree) = parse("g();\nfunction f() { g(); }\n");
+        let locations = references(&uri, &content, &tree, position(&content, "g"), true).unwrap().unwrap();
+        assert_eq!(locations.len(), 2);
+        assert_eq!(references(&uri, &content, &tree, position(&content, ";"), true).unwrap(), None);
+    }
+}
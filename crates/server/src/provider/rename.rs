@@ -0,0 +1,312 @@
+use crate::core::scope::{BindingId, Occurrence, Scopes};
+use lsp_text::RopeExt;
+use std::collections::HashMap;
+
+/// Words which cannot be used as binding names.
+pub static RESERVED_WORDS: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Whether `name` is syntactically an identifier.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let start = match chars.next() {
+        Some(start) => start,
+        None => return false,
+    };
+    let is_start = |c: char| c == '$' || c == '_' || c.is_alphabetic();
+    let is_continue = |c: char| is_start(c) || c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}';
+    is_start(start) && chars.all(is_continue)
+}
+
+/// Check that `name` can be used as the name of a binding.
+pub fn validate_name(name: &str) -> anyhow::Result<()> {
+    let name = name.to_string();
+    if !is_identifier(&name) {
+        return Err(crate::core::Error::InvalidIdentifier { name }.into());
+    }
+    if RESERVED_WORDS.contains(&name.as_str()) {
+        return Err(crate::core::Error::ReservedWord { name }.into());
+    }
+    Ok(())
+}
+
+pub fn prepare_rename(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+) -> anyhow::Result<Option<lsp::PrepareRenameResponse>> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+    let scopes = Scopes::analyze(content, tree);
+    let occurrence = match scopes.occurrence_at(byte) {
+        Some(occurrence) => occurrence,
+        None => return Ok(None),
+    };
+    if scopes.binding_of(occurrence).is_none() {
+        let name = scopes.name_of(occurrence).to_string();
+        return Err(crate::core::Error::RenameUnresolved { name }.into());
+    }
+    let range = scopes.range_of(occurrence);
+    let placeholder = scopes.name_of(occurrence).to_string();
+    Ok(Some(lsp::PrepareRenameResponse::RangeWithPlaceholder { range, placeholder }))
+}
+
+pub fn rename(
+    uri: &lsp::Url,
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+    new_name: &str,
+) -> anyhow::Result<Option<lsp::WorkspaceEdit>> {
+    validate_name(new_name)?;
+
+    let byte = content.lsp_position_to_core(position)?.byte;
+    let scopes = Scopes::analyze(content, tree);
+    let occurrence = match scopes.occurrence_at(byte) {
+        Some(occurrence) => occurrence,
+        None => return Ok(None),
+    };
+    let binding = match scopes.binding_of(occurrence) {
+        Some(binding) => binding,
+        None => {
+            let name = scopes.name_of(occurrence).to_string();
+            return Err(crate::core::Error::RenameUnresolved { name }.into());
+        },
+    };
+
+    let old_name = scopes.bindings[binding].name.clone();
+    if old_name == new_name {
+        return Ok(None);
+    }
+
+    if has_conflict(&scopes, binding, new_name) {
+        let name = new_name.to_string();
+        return Err(crate::core::Error::RenameConflict { name }.into());
+    }
+
+    let root = tree.root_node();
+    let edits = scopes
+        .related(Occurrence::Binding(binding))
+        .into_iter()
+        .map(|occurrence| {
+            let range = scopes.range_of(occurrence);
+            let span = scopes.span_of(occurrence);
+            let node = root.named_descendant_for_byte_range(span.start, span.end);
+            let new_text = node
+                .map(|node| replacement(&node, &old_name, new_name))
+                .unwrap_or_else(|| new_name.to_string());
+            lsp::TextEdit { range, new_text }
+        })
+        .collect();
+
+    let changes = Some(HashMap::from([(uri.clone(), edits)]));
+    Ok(Some(lsp::WorkspaceEdit {
+        changes,
+        ..Default::default()
+    }))
+}
+
+/// Whether renaming the binding to `new_name` would change what any name in the document refers
+/// to.
+fn has_conflict(scopes: &Scopes, binding: BindingId, new_name: &str) -> bool {
+    let scope = scopes.bindings[binding].scope;
+
+    // A binding with the new name already exists in the same scope.
+    if scopes.lookup_local(scope, new_name).is_some() {
+        return true;
+    }
+
+    // A reference to the binding would be captured by a binding with the new name declared in a
+    // scope nested between the reference and the binding.
+    let captured = scopes.bindings[binding].references.iter().any(|&reference| {
+        scopes
+            .ancestors(scopes.references[reference].scope)
+            .take_while(|&ancestor| ancestor != scope)
+            .any(|ancestor| scopes.lookup_local(ancestor, new_name).is_some())
+    });
+    if captured {
+        return true;
+    }
+
+    // A reference to an outer binding (or a global) with the new name would be shadowed by the
+    // renamed binding.
+    scopes.references.iter().any(|reference| {
+        if reference.name != new_name || !scopes.ancestors(reference.scope).any(|ancestor| ancestor == scope) {
+            return false;
+        }
+        match reference.binding {
+            Some(other) => {
+                let other = scopes.bindings[other].scope;
+                other != scope && scopes.ancestors(scope).any(|ancestor| ancestor == other)
+            },
+            None => true,
+        }
+    })
+}
+
+/// Compute the text replacing an occurrence of the binding, preserving the meaning of shorthand
+/// properties and of import and export specifiers which use the name of the binding.
+fn replacement(node: &tree_sitter::Node, old_name: &str, new_name: &str) -> String {
+    let parent = node.parent();
+    let has_alias = |parent: &tree_sitter::Node| parent.child_by_field_name("alias").is_some();
+    match &*node.kind() {
+        // `{ foo }` becomes `{ foo: bar }`
+        "shorthand_property_identifier" | "shorthand_property_identifier_pattern" => {
+            format!("{}: {}", old_name, new_name)
+        },
+        // `import { foo }` becomes `import { foo as bar }`
+        _ if parent.as_ref().map_or(false, |parent| parent.kind() == "import_specifier" && !has_alias(parent)) => {
+            format!("{} as {}", old_name, new_name)
+        },
+        // `export { foo }` becomes `export { bar as foo }`
+        _ if parent.as_ref().map_or(false, |parent| parent.kind() == "export_specifier" && !has_alias(parent)) => {
+            format!("{} as {}", new_name, old_name)
+        },
+        _ => new_name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_identifier, validate_name};
+
+    #[test]
+    fn identifiers() {
+        assert!(is_identifier("foo"));
+        assert!(is_identifier("$foo_1"));
+        assert!(is_identifier("_"));
+        assert!(is_identifier("ünïcödé"));
+        assert!(!is_identifier(""));
+        assert!(!is_identifier("1foo"));
+        assert!(!is_identifier("foo-bar"));
+        assert!(!is_identifier("foo bar"));
+    }
+
+    #[test]
+    fn reserved_words() {
+        assert!(validate_name("klass").is_ok());
+        assert!(validate_name("class").is_err());
+        assert!(validate_name("yield").is_err());
+    }
+}
+
+#[cfg(all(test, feature = "native"))]
+mod parsing_tests {
+    use super::{prepare_rename, rename};
+    use crate::core::{
+        test::{apply, parse, position},
+        Error,
+    };
+
+    /// Rename the name at the first occurrence of `at`, returning the resulting text.
+    fn renamed(source: &str, at: &str, new_name: &str) -> anyhow::Result<String> {
+        let uri = lsp::Url::parse("file:///a.js").unwrap();
+        let (content, tree) = parse(source);
+        let edit = rename(&uri, &content, &tree, position(&content, at), new_name)?.unwrap();
+        Ok(apply(&content, &edit.changes.unwrap()[&uri]))
+    }
+
+    fn error(result: anyhow::Result<String>) -> Error {
+        result.unwrap_err().downcast::<Error>().unwrap()
+    }
+
+    #[test]
+    fn renames_the_binding_and_its_references() {
+        let source = "let a = 1;\nfunction f(a) { return a; }\na = a + 1;\n";
+        let expected = "let b = 1;\nfunction f(a) { return a; }\nb = b + 1;\n";
+        assert_eq!(renamed(source, "a + 1", "b").unwrap(), expected);
+    }
+
+    #[test]
+    fn expands_shorthand_properties() {
+        let source = "let a = 1;\nlet o = { a };\nlet { c } = o;\nc;\n";
+        assert_eq!(renamed(source, "a = 1", "b").unwrap(), "let b = 1;\nlet o = { a: b };\nlet { c } = o;\nc;\n");
+        assert_eq!(renamed(source, "c;", "d").unwrap(), "let a = 1;\nlet o = { a };\nlet { c: d } = o;\nd;\n");
+    }
+
+    #[test]
+    fn keeps_import_and_export_names() {
+        let source = "import { a } from 'm';\nexport { a };\na;\n";
+        let expected = "import { a as b } from 'm';\nexport { b as a };\nb;\n";
+        assert_eq!(renamed(source, "a;", "b").unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_conflicts() {
+        let conflict = Error::RenameConflict { name: "b".into() };
+        // A binding with the new name in the same scope.
+        assert_eq!(error(renamed("let a, b;\n", "a", "b")), conflict);
+        // A reference which would be captured by an inner binding.
+        assert_eq!(error(renamed("let a;\nfunction f() { let b; a; }\n", "a", "b")), conflict);
+        // A reference to an outer binding or global which would be shadowed.
+        assert_eq!(error(renamed("let b;\nfunction f() { let a; b; }\n", "a;", "b")), conflict);
+        assert_eq!(error(renamed("function f() { let a; b; }\n", "a;", "b")), conflict);
+    }
+
+    #[test]
+    fn rejects_unresolved_names() {
+        let uri = lsp::Url::parse("file:///a.js").unwrap();
+        let (content, tree) = parse("foo();\n");
+        let unresolved = Error::RenameUnresolved { name: "foo".into() };
+        let error = prepare_rename(&content, &tree, position(&content, "foo")).unwrap_err();
+        assert_eq!(error.downcast::<Error>().unwrap(), unresolved);
+        let error = rename(&uri, &content, &tree, position(&content, "foo"), "bar").unwrap_err();
+        assert_eq!(error.downcast::<Error>().unwrap(), unresolved);
+    }
+
+    #[test]
+    fn prepares_resolved_names() {
+        let (content, tree) = parse("let foo;\nfoo();\n");
+        let response = prepare_rename(&content, &tree, position(&content, "foo()")).unwrap().unwrap();
+        let range = lsp::Range::new(lsp::Position::new(1, 0), lsp::Position::new(1, 3));
+        let placeholder = "foo".to_string();
+        assert_eq!(response, lsp::PrepareRenameResponse::RangeWithPlaceholder { range, placeholder });
+    }
+}
@@ -0,0 +1,94 @@
+use lsp_text::RopeExt;
+
+/// Node kinds whose contents, without the surrounding delimiters, form an extra selection step.
+static DELIMITED: &[&str] = &[
+    "arguments",
+    "array",
+    "class_body",
+    "formal_parameters",
+    "named_imports",
+    "object",
+    "object_pattern",
+    "parenthesized_expression",
+    "statement_block",
+    "switch_body",
+];
+
+pub fn selection_ranges(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    positions: Vec<lsp::Position>,
+) -> anyhow::Result<Vec<lsp::SelectionRange>> {
+    positions
+        .into_iter()
+        .map(|position| selection_range(content, tree, position))
+        .collect()
+}
+
+fn selection_range(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+) -> anyhow::Result<lsp::SelectionRange> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+
+    // NOTE: ranges are collected from the innermost outwards.
+    let mut ranges: Vec<lsp::Range> = vec![];
+    let mut push = |range: lsp::Range| {
+        if ranges.last() != Some(&range) {
+            ranges.push(range);
+        }
+    };
+
+    let mut node = tree.root_node().named_descendant_for_byte_range(byte, byte);
+    while let Some(current) = node {
+        if let Some(inner) = inner_range(content, &current) {
+            if contains(&inner, position) {
+                push(inner);
+            }
+        }
+        push(content.tree_sitter_range_to_lsp_range(current.range()));
+        node = current.parent();
+    }
+
+    let selection_range = ranges
+        .into_iter()
+        .rev()
+        .fold(None, |parent, range| {
+            let parent = parent.map(Box::new);
+            Some(lsp::SelectionRange { range, parent })
+        })
+        .unwrap_or_else(|| {
+            let range = lsp::Range::new(position, position);
+            let parent = None;
+            lsp::SelectionRange { range, parent }
+        });
+
+    Ok(selection_range)
+}
+
+/// The range of the contents of a string or of a delimited node, excluding the delimiters.
+fn inner_range(content: &ropey::Rope, node: &tree_sitter::Node) -> Option<lsp::Range> {
+    let kind = node.kind();
+    match &*kind {
+        "string" | "template_string" => {
+            let open = node.child(0).filter(|child| !child.is_named())?;
+            let close = node.child(node.child_count().checked_sub(1)?).filter(|child| !child.is_named())?;
+            let start = content.tree_sitter_range_to_lsp_range(open.range()).end;
+            let end = content.tree_sitter_range_to_lsp_range(close.range()).start;
+            Some(lsp::Range::new(start, end))
+        },
+        kind if DELIMITED.contains(&kind) => {
+            let first = node.named_child(0)?;
+            let last = node.named_child(node.named_child_count() - 1)?;
+            let start = content.tree_sitter_range_to_lsp_range(first.range()).start;
+            let end = content.tree_sitter_range_to_lsp_range(last.range()).end;
+            Some(lsp::Range::new(start, end))
+        },
+        _ => None,
+    }
+}
+
+fn contains(range: &lsp::Range, position: lsp::Position) -> bool {
+    range.start <= position && position <= range.end
+}
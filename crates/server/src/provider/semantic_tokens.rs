@@ -0,0 +1,482 @@
+use crate::core::scope::{BindingId, BindingKind, Scopes};
+use lsp_text::RopeExt;
+use std::collections::HashSet;
+
+pub const TOKEN_TYPES: &[lsp::SemanticTokenType] = &[
+    lsp::SemanticTokenType::KEYWORD,
+    lsp::SemanticTokenType::FUNCTION,
+    lsp::SemanticTokenType::METHOD,
+    lsp::SemanticTokenType::PARAMETER,
+    lsp::SemanticTokenType::VARIABLE,
+    lsp::SemanticTokenType::PROPERTY,
+    lsp::SemanticTokenType::CLASS,
+    lsp::SemanticTokenType::STRING,
+    lsp::SemanticTokenType::NUMBER,
+    lsp::SemanticTokenType::COMMENT,
+    lsp::SemanticTokenType::REGEXP,
+    lsp::SemanticTokenType::OPERATOR,
+];
+
+pub const TOKEN_MODIFIERS: &[lsp::SemanticTokenModifier] = &[
+    lsp::SemanticTokenModifier::DECLARATION,
+    lsp::SemanticTokenModifier::READONLY,
+    lsp::SemanticTokenModifier::STATIC,
+];
+
+static KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "get",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "of",
+    "return",
+    "set",
+    "static",
+    "switch",
+    "target",
+    "throw",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+static OPERATORS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "**=", "<<=", ">>=", ">>>=", "&=", "|=", "^=", "&&=", "||=", "??=", "+", "-",
+    "*", "/", "%", "**", "<<", ">>", ">>>", "&", "|", "^", "&&", "||", "??", "!", "~", "==", "===", "!=", "!==", "<",
+    "<=", ">", ">=", "++", "--", "=>", "?", "...", "?.",
+];
+
+pub fn legend() -> lsp::SemanticTokensLegend {
+    lsp::SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// The most recently computed semantic tokens for a document.
+pub struct Cache {
+    /// The version of the document the tokens were computed from.
+    pub version: i32,
+    pub tokens: Vec<lsp::SemanticToken>,
+}
+
+impl Cache {
+    /// The identifier of the tokens, which is the version they were computed from since versions
+    /// increase with every change to the document.
+    pub fn result_id(&self) -> String {
+        self.version.to_string()
+    }
+}
+
+/// Compute the semantic tokens for the document, optionally restricted to the lines spanned by
+/// `range`.
+pub fn tokens(
+    legend: &lsp::SemanticTokensLegend,
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    range: Option<lsp::Range>,
+) -> Vec<lsp::SemanticToken> {
+    // NOTE: uses of parameters, and parameters declared within patterns, are indistinguishable from
+    // variables in the syntax tree, so they are found through the scope analysis.
+    let scopes = Scopes::analyze(content, tree);
+    let is_parameter = |binding: BindingId| scopes.bindings[binding].kind == BindingKind::Parameter;
+    let declarations = (0 .. scopes.bindings.len()).filter(|&binding| is_parameter(binding));
+    let references = scopes.references.iter().filter(|reference| reference.binding.map_or(false, is_parameter));
+    let parameters = declarations
+        .map(|binding| scopes.bindings[binding].span.start)
+        .chain(references.map(|reference| reference.span.start))
+        .collect();
+
+    let mut builder = Builder {
+        legend,
+        content,
+        range,
+        parameters,
+        previous: lsp::Position::default(),
+        tokens: vec![],
+    };
+    builder.visit(&tree.root_node());
+    builder.tokens
+}
+
+/// Compute the edits which transform the `old` tokens into the `new` tokens.
+pub fn edits(old: &[lsp::SemanticToken], new: &[lsp::SemanticToken]) -> Vec<lsp::SemanticTokensEdit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix ..]
+        .iter()
+        .rev()
+        .zip(new[prefix ..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let deleted = old.len() - prefix - suffix;
+    let inserted = &new[prefix .. new.len() - suffix];
+    if deleted == 0 && inserted.is_empty() {
+        return vec![];
+    }
+
+    // NOTE: edit offsets count integers in the flattened encoding; each token is 5 integers.
+    let start = (prefix * 5) as u32;
+    let delete_count = (deleted * 5) as u32;
+    let data = Some(inserted.to_vec());
+    vec![lsp::SemanticTokensEdit {
+        start,
+        delete_count,
+        data,
+    }]
+}
+
+struct Builder<'a> {
+    legend: &'a lsp::SemanticTokensLegend,
+    content: &'a ropey::Rope,
+    range: Option<lsp::Range>,
+    /// The start bytes of the parameters and of the references to them.
+    parameters: HashSet<u32>,
+    previous: lsp::Position,
+    tokens: Vec<lsp::SemanticToken>,
+}
+
+impl<'a> Builder<'a> {
+    fn visit(&mut self, node: &tree_sitter::Node) {
+        if let Some(range) = self.range {
+            if node.end_position().row() < range.start.line || node.start_position().row() > range.end.line {
+                return;
+            }
+        }
+
+        let kind = node.kind();
+        match &*kind {
+            "comment" => self.push_node(node, lsp::SemanticTokenType::COMMENT, &[]),
+            "string" => self.push_node(node, lsp::SemanticTokenType::STRING, &[]),
+            "number" => self.push_node(node, lsp::SemanticTokenType::NUMBER, &[]),
+            "regex" => self.push_node(node, lsp::SemanticTokenType::REGEXP, &[]),
+            "template_string" => self.visit_template_string(node),
+            "this" | "super" | "true" | "false" | "null" | "undefined" => {
+                self.push_node(node, lsp::SemanticTokenType::KEYWORD, &[]);
+            },
+            "identifier"
+            | "property_identifier"
+            | "private_property_identifier"
+            | "shorthand_property_identifier"
+            | "shorthand_property_identifier_pattern" => {
+                if let Some((token_type, token_modifiers)) = classify_identifier(node) {
+                    let is_parameter = token_type == lsp::SemanticTokenType::VARIABLE
+                        && self.parameters.contains(&node.start_byte());
+                    let token_type = if is_parameter {
+                        lsp::SemanticTokenType::PARAMETER
+                    } else {
+                        token_type
+                    };
+                    self.push_node(node, token_type, &token_modifiers);
+                }
+            },
+            kind if !node.is_named() && KEYWORDS.contains(&kind) => {
+                self.push_node(node, lsp::SemanticTokenType::KEYWORD, &[]);
+            },
+            kind if !node.is_named() && OPERATORS.contains(&kind) => {
+                self.push_node(node, lsp::SemanticTokenType::OPERATOR, &[]);
+            },
+            _ => {
+                for i in 0 .. node.child_count() {
+                    if let Some(child) = node.child(i) {
+                        self.visit(&child);
+                    }
+                }
+            },
+        }
+    }
+
+    // NOTE: only the literal parts of a template string are string tokens; the substitutions are
+    // tokenized like any other expression.
+    fn visit_template_string(&mut self, node: &tree_sitter::Node) {
+        let mut start = self.content.tree_sitter_range_to_lsp_range(node.range()).start;
+        for i in 0 .. node.named_child_count() {
+            if let Some(child) = node.named_child(i) {
+                if child.kind() == "template_substitution" {
+                    let range = self.content.tree_sitter_range_to_lsp_range(child.range());
+                    self.push_range(lsp::Range::new(start, range.start), lsp::SemanticTokenType::STRING, &[]);
+                    self.visit(&child);
+                    start = range.end;
+                }
+            }
+        }
+        let end = self.content.tree_sitter_range_to_lsp_range(node.range()).end;
+        self.push_range(lsp::Range::new(start, end), lsp::SemanticTokenType::STRING, &[]);
+    }
+
+    fn push_node(
+        &mut self,
+        node: &tree_sitter::Node,
+        token_type: lsp::SemanticTokenType,
+        token_modifiers: &[lsp::SemanticTokenModifier],
+    ) {
+        let range = self.content.tree_sitter_range_to_lsp_range(node.range());
+        self.push_range(range, token_type, token_modifiers);
+    }
+
+    // NOTE: tokens spanning multiple lines are split into one token per line since not every
+    // client supports multiline tokens.
+    fn push_range(
+        &mut self,
+        range: lsp::Range,
+        token_type: lsp::SemanticTokenType,
+        token_modifiers: &[lsp::SemanticTokenModifier],
+    ) {
+        let token_type = match self.legend.token_types.iter().position(|t| *t == token_type) {
+            Some(index) => index as u32,
+            None => return,
+        };
+        let token_modifiers_bitset = token_modifiers
+            .iter()
+            .filter_map(|modifier| self.legend.token_modifiers.iter().position(|m| m == modifier))
+            .fold(0, |bitset, index| bitset | (1 << index));
+
+        for line in range.start.line ..= range.end.line {
+            let start = if line == range.start.line { range.start.character } else { 0 };
+            let end = if line == range.end.line {
+                range.end.character
+            } else {
+                self.line_length(line)
+            };
+            if end <= start {
+                continue;
+            }
+            if let Some(range) = self.range {
+                if line < range.start.line || line > range.end.line {
+                    continue;
+                }
+            }
+
+            let delta_line = line - self.previous.line;
+            let delta_start = if delta_line == 0 {
+                start - self.previous.character
+            } else {
+                start
+            };
+            self.tokens.push(lsp::SemanticToken {
+                delta_line,
+                delta_start,
+                length: end - start,
+                token_type,
+                token_modifiers_bitset,
+            });
+            self.previous = lsp::Position::new(line, start);
+        }
+    }
+
+    /// The length of the line in UTF-16 code units, excluding the line terminator.
+    fn line_length(&self, line: u32) -> u32 {
+        let line = self.content.line(line as usize).to_string();
+        line.trim_end_matches(|c| c == '\n' || c == '\r').encode_utf16().count() as u32
+    }
+}
+
+fn classify_identifier(node: &tree_sitter::Node) -> Option<(lsp::SemanticTokenType, Vec<lsp::SemanticTokenModifier>)> {
+    use lsp::{SemanticTokenModifier as Modifier, SemanticTokenType as Type};
+
+    let parent = node.parent()?;
+    let is_field = |name: &str| {
+        parent
+            .child_by_field_name(name)
+            .map_or(false, |child| child.start_byte() == node.start_byte())
+    };
+    let declaration = Modifier::DECLARATION;
+
+    let result = match &*parent.kind() {
+        "function_declaration" | "generator_function_declaration" | "function" | "generator_function"
+            if is_field("name") =>
+        {
+            (Type::FUNCTION, vec![declaration])
+        },
+        "class_declaration" | "class" if is_field("name") => (Type::CLASS, vec![declaration]),
+        "method_definition" if is_field("name") => {
+            let mut modifiers = vec![declaration];
+            if has_keyword(&parent, "static") {
+                modifiers.push(Modifier::STATIC);
+            }
+            (Type::METHOD, modifiers)
+        },
+        "field_definition" | "public_field_definition" if is_field("property") => {
+            let mut modifiers = vec![declaration];
+            if has_keyword(&parent, "static") {
+                modifiers.push(Modifier::STATIC);
+            }
+            (Type::PROPERTY, modifiers)
+        },
+        "pair" if is_field("key") => (Type::PROPERTY, vec![declaration]),
+        "variable_declarator" if is_field("name") => {
+            let mut modifiers = vec![declaration];
+            let is_const = parent
+                .parent()
+                .and_then(|declaration| declaration.child(0))
+                .map_or(false, |keyword| keyword.kind() == "const");
+            if is_const {
+                modifiers.push(Modifier::READONLY);
+            }
+            let is_function = parent.child_by_field_name("value").map_or(false, |value| {
+                matches!(&*value.kind(), "arrow_function" | "function" | "generator_function")
+            });
+            if is_function {
+                (Type::FUNCTION, modifiers)
+            } else {
+                (Type::VARIABLE, modifiers)
+            }
+        },
+        "formal_parameters" => (Type::PARAMETER, vec![declaration]),
+        "rest_pattern" if is_parameter(&parent) => (Type::PARAMETER, vec![declaration]),
+        "assignment_pattern" if is_field("left") && is_parameter(&parent) => (Type::PARAMETER, vec![declaration]),
+        "arrow_function" if is_field("parameter") => (Type::PARAMETER, vec![declaration]),
+        "call_expression" if is_field("function") => (Type::FUNCTION, vec![]),
+        "new_expression" if is_field("constructor") => (Type::CLASS, vec![]),
+        "class_heritage" => (Type::CLASS, vec![]),
+        "member_expression" if is_field("property") => {
+            let is_callee = parent.parent().map_or(false, |grandparent| {
+                grandparent.kind() == "call_expression"
+                    && grandparent
+                        .child_by_field_name("function")
+                        .map_or(false, |function| function.start_byte() == parent.start_byte())
+            });
+            if is_callee {
+                (Type::METHOD, vec![])
+            } else {
+                (Type::PROPERTY, vec![])
+            }
+        },
+        "statement_identifier" | "labeled_statement" | "break_statement" | "continue_statement" => return None,
+        _ => match &*node.kind() {
+            "property_identifier" | "private_property_identifier" => (Type::PROPERTY, vec![]),
+            "shorthand_property_identifier_pattern" => (Type::VARIABLE, vec![declaration]),
+            _ => (Type::VARIABLE, vec![]),
+        },
+    };
+
+    Some(result)
+}
+
+fn is_parameter(pattern: &tree_sitter::Node) -> bool {
+    pattern
+        .parent()
+        .map_or(false, |parent| parent.kind() == "formal_parameters")
+}
+
+fn has_keyword(node: &tree_sitter::Node, keyword: &str) -> bool {
+    (0 .. node.child_count())
+        .filter_map(|i| node.child(i))
+        .any(|child| !child.is_named() && child.kind() == keyword)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::edits;
+
+    fn token(delta_line: u32, delta_start: u32, length: u32) -> lsp::SemanticToken {
+        lsp::SemanticToken {
+            delta_line,
+            delta_start,
+            length,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    #[test]
+    fn edits_unchanged() {
+        let old = vec![token(0, 0, 3), token(1, 2, 4)];
+        assert!(edits(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn edits_replace_middle() {
+        let old = vec![token(0, 0, 3), token(1, 2, 4), token(1, 0, 5)];
+        let new = vec![token(0, 0, 3), token(1, 2, 6), token(0, 7, 1), token(1, 0, 5)];
+        let expected = vec![lsp::SemanticTokensEdit {
+            start: 5,
+            delete_count: 5,
+            data: Some(vec![token(1, 2, 6), token(0, 7, 1)]),
+        }];
+        assert_eq!(expected, edits(&old, &new));
+    }
+}
+
+#[cfg(all(test, feature = "native"))]
+mod parsing_tests {
+    use super::{legend, tokens};
+    use crate::core::test::parse;
+
+    /// Return the type and modifiers of the tokens, with their text.
+    fn classify(source: &str) -> Vec<(String, lsp::SemanticTokenType, u32)> {
+        let legend = legend();
+        let (content, tree) = parse(source);
+        let mut position = lsp::Position::default();
+        tokens(&legend, &content, &tree, None)
+            .into_iter()
+            .map(|token| {
+                if token.delta_line > 0 {
+                    position = lsp::Position::new(position.line + token.delta_line, token.delta_start);
+                } else {
+                    position.character += token.delta_start;
+                }
+                let line = content.line(position.line as usize).to_string();
+                let start = position.character as usize;
+                let text = line[start .. start + token.length as usize].to_string();
+                let token_type = legend.token_types[token.token_type as usize].clone();
+                (text, token_type, token.token_modifiers_bitset)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parameter_uses() {
+        use lsp::SemanticTokenType as Type;
+
+        let source = "let c;\nfunction f(a, { b }, ...d) {\n  return a + b + c + d({ a });\n}\n";
+        let identifiers: Vec<_> = classify(source)
+            .into_iter()
+            .filter(|(text, ..)| matches!(text.as_str(), "a" | "b" | "c" | "d"))
+            .map(|(text, token_type, modifiers)| (text, token_type, modifiers != 0))
+            .collect();
+        let expected = [
+            ("c", Type::VARIABLE, true),
+            ("a", Type::PARAMETER, true),
+            ("b", Type::PARAMETER, true),
+            ("d", Type::PARAMETER, true),
+            ("a", Type::PARAMETER, false),
+            ("b", Type::PARAMETER, false),
+            ("c", Type::VARIABLE, false),
+            ("d", Type::FUNCTION, false),
+            ("a", Type::PARAMETER, false),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(text, token_type, declaration)| (text.to_string(), token_type, declaration))
+            .collect();
+        assert_eq!(identifiers, expected);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::core::{declaration, scope::Scopes};
+use lsp_text::RopeExt;
+
+/// Client settings which affect the signature help the server reports.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    pub markdown: bool,
+}
+
+impl Options {
+    pub fn new(capabilities: Option<&lsp::ClientCapabilities>) -> Self {
+        let markdown = capabilities
+            .and_then(|capabilities| capabilities.text_document.as_ref())
+            .and_then(|capabilities| capabilities.signature_help.as_ref())
+            .and_then(|capabilities| capabilities.signature_information.as_ref())
+            .and_then(|capabilities| capabilities.documentation_format.as_ref())
+            .map_or(false, |formats| formats.first() == Some(&lsp::MarkupKind::Markdown));
+        Options { markdown }
+    }
+}
+
+pub fn signature_help(
+    content: &ropey::Rope,
+    tree: &tree_sitter::Tree,
+    position: lsp::Position,
+    options: Options,
+) -> anyhow::Result<Option<lsp::SignatureHelp>> {
+    let byte = content.lsp_position_to_core(position)?.byte;
+
+    let arguments = match enclosing_arguments(tree, byte) {
+        Some(arguments) => arguments,
+        None => return Ok(None),
+    };
+    let call = match arguments.parent() {
+        Some(call) => call,
+        None => return Ok(None),
+    };
+    let field = if call.kind() == "new_expression" {
+        "constructor"
+    } else {
+        "function"
+    };
+    let callee = match call.child_by_field_name(field) {
+        Some(callee) => callee,
+        None => return Ok(None),
+    };
+
+    let scopes = Scopes::analyze(content, tree);
+    let callee = match declaration::callee(content, tree, &scopes, &callee) {
+        Some(callee) => callee,
+        None => return Ok(None),
+    };
+
+    let documentation = declaration::documentation(content, &callee.declaration);
+    let markup = |value: String| {
+        let kind = if options.markdown {
+            lsp::MarkupKind::Markdown
+        } else {
+            lsp::MarkupKind::PlainText
+        };
+        lsp::Documentation::MarkupContent(lsp::MarkupContent { kind, value })
+    };
+
+    let parameters = declaration::parameters(&callee.function);
+    let labels = parameters
+        .iter()
+        .map(|parameter| declaration::single_line(&content.utf8_text_for_tree_sitter_node(parameter)))
+        .collect::<Vec<_>>();
+    let label = format!("{}({})", callee.name, labels.join(", "));
+
+    let parameter_information = parameters
+        .iter()
+        .zip(labels)
+        .map(|(parameter, label)| {
+            let name = declaration::parameter_name(content, parameter);
+            let name = name.as_deref().map(|name| name.trim_start_matches("..."));
+            let description = documentation
+                .as_ref()
+                .zip(name)
+                .and_then(|(documentation, name)| documentation.param(name))
+                .filter(|description| !description.is_empty());
+            lsp::ParameterInformation {
+                label: lsp::ParameterLabel::Simple(label),
+                documentation: description.map(|description| markup(description.to_string())),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // NOTE: arguments past a rest parameter all belong to it.
+    let mut active_parameter = active_argument(&arguments, byte);
+    let is_rest = parameters.last().map_or(false, |parameter| parameter.kind() == "rest_pattern");
+    if is_rest {
+        active_parameter = active_parameter.min(parameters.len() as u32 - 1);
+    }
+
+    let description = documentation
+        .map(|documentation| documentation.description)
+        .filter(|description| !description.is_empty());
+    let signature = lsp::SignatureInformation {
+        label,
+        documentation: description.map(markup),
+        parameters: Some(parameter_information),
+        active_parameter: Some(active_parameter),
+    };
+
+    Ok(Some(lsp::SignatureHelp {
+        signatures: vec![signature],
+        active_signature: Some(0),
+        active_parameter: Some(active_parameter),
+    }))
+}
+
+/// Find the innermost argument list whose parentheses enclose the offset.
+fn enclosing_arguments(tree: &tree_sitter::Tree, byte: u32) -> Option<tree_sitter::Node> {
+    let node = tree.root_node().descendant_for_byte_range(byte, byte)?;
+    std::iter::successors(Some(node), |node| node.parent()).find(|node| {
+        if node.kind() != "arguments" || byte <= node.start_byte() {
+            return false;
+        }
+        // NOTE: an unclosed argument list extends to the end of the node.
+        let is_closed = node
+            .child(node.child_count().saturating_sub(1))
+            .map_or(false, |close| close.kind() == ")" && !close.is_missing());
+        byte < node.end_byte() || !is_closed
+    })
+}
+
+/// Return the index of the argument containing the offset, by counting the preceding commas.
+fn active_argument(arguments: &tree_sitter::Node, byte: u32) -> u32 {
+    (0 .. arguments.child_count())
+        .filter_map(|i| arguments.child(i))
+        .filter(|child| child.kind() == "," && child.end_byte() <= byte)
+        .count() as u32
+}
@@ -0,0 +1,218 @@
+use dashmap::DashMap;
+use std::cmp::Reverse;
+
+/// The maximum number of symbols returned for a query.
+pub static MAX_RESULTS: usize = 256;
+
+/// A symbol recorded in the workspace symbol index.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub name: String,
+    pub kind: lsp::SymbolKind,
+    pub container_name: Option<String>,
+    /// The range of the name of the symbol.
+    pub range: lsp::Range,
+}
+
+/// The symbols of every open document, updated whenever a document is opened, changed or closed.
+pub type Index = DashMap<lsp::Url, Vec<Entry>>;
+
+/// Flatten a symbol outline into index entries, recording the name of each parent as the
+/// container name.
+pub fn entries(symbols: &[lsp::DocumentSymbol]) -> Vec<Entry> {
+    fn visit(container_name: Option<&str>, symbol: &lsp::DocumentSymbol, entries: &mut Vec<Entry>) {
+        entries.push(Entry {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            container_name: container_name.map(Into::into),
+            range: symbol.selection_range,
+        });
+        for child in symbol.children.iter().flatten() {
+            visit(Some(&symbol.name), child, entries);
+        }
+    }
+
+    let mut entries = vec![];
+    for symbol in symbols {
+        visit(None, symbol, &mut entries);
+    }
+    entries
+}
+
+/// Search the index for symbols matching the query, best matches first.
+pub fn symbols(index: &Index, query: &str) -> Vec<lsp::SymbolInformation> {
+    let mut matches = vec![];
+    for document in index.iter() {
+        for entry in document.value() {
+            if let Some(score) = score(query, &entry.name) {
+                matches.push((score, document.key().clone(), entry.clone()));
+            }
+        }
+    }
+    matches.sort_by(|(a_score, a_uri, a), (b_score, b_uri, b)| {
+        let key = |score: &u32, entry: &Entry| (Reverse(*score), entry.name.len());
+        key(a_score, a)
+            .cmp(&key(b_score, b))
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a_uri.cmp(b_uri))
+    });
+    matches.truncate(MAX_RESULTS);
+
+    // NOTE: locations are only built for the symbols which are returned.
+    matches
+        .into_iter()
+        .map(|(_, uri, entry)| {
+            #[allow(deprecated)]
+            lsp::SymbolInformation {
+                name: entry.name,
+                kind: entry.kind,
+                tags: Default::default(),
+                deprecated: Default::default(),
+                location: lsp::Location::new(uri, entry.range),
+                container_name: entry.container_name,
+            }
+        })
+        .collect()
+}
+
+/// Fill in the range of a symbol whose location only names its document, looking the symbol up
+/// by its name and container.
+pub fn resolve(index: &Index, mut symbol: lsp::WorkspaceSymbol) -> lsp::WorkspaceSymbol {
+    let uri = match &symbol.location {
+        lsp::OneOf::Left(_) => return symbol,
+        lsp::OneOf::Right(location) => location.uri.clone(),
+    };
+    let range = index.get(&uri).and_then(|entries| {
+        entries
+            .iter()
+            .find(|entry| entry.name == symbol.name && entry.container_name == symbol.container_name)
+            .map(|entry| entry.range)
+    });
+    if let Some(range) = range {
+        symbol.location = lsp::OneOf::Left(lsp::Location::new(uri, range));
+    }
+    symbol
+}
+
+/// Score how well the query matches the name, ignoring case, or return [`None`] if it does not.
+///
+/// From best to worst, the query may be the name, a prefix of it, the initials of its words
+/// (camel humps, e.g., `gUN` or `gun` for `getUserName`), a substring of it, or a subsequence of
+/// it. Within each tier, matches at word boundaries score higher.
+pub fn score(query: &str, name: &str) -> Option<u32> {
+    let query = query.chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let chars = name.chars().collect::<Vec<_>>();
+    let lower = |c: &char| c.to_lowercase().collect::<String>();
+    let query = query.iter().map(lower).collect::<Vec<_>>();
+    let folded = chars.iter().map(lower).collect::<Vec<_>>();
+
+    if folded == query {
+        return Some(5000);
+    }
+    if folded.starts_with(&query) {
+        return Some(4000);
+    }
+    let starts = word_starts(&chars);
+    if humps(&query, &folded, &starts, 0) {
+        return Some(3000);
+    }
+    if let Some(position) = folded.windows(query.len()).position(|window| window == query.as_slice()) {
+        let bonus = if starts.contains(&position) { 500 } else { 0 };
+        return Some(2000 + bonus - position.min(499) as u32);
+    }
+
+    // NOTE: a subsequence scores by the number of its characters at word boundaries and loses
+    // points for the characters skipped in between.
+    let mut score = 1000i64;
+    let mut next = 0;
+    let mut previous = None;
+    for c in &query {
+        let position = (next .. folded.len()).find(|&i| &folded[i] == c)?;
+        if starts.contains(&position) {
+            score += 20;
+        }
+        if let Some(previous) = previous {
+            score -= (position - previous - 1) as i64;
+        }
+        previous = Some(position);
+        next = position + 1;
+    }
+    Some(score.clamp(1, 1999) as u32)
+}
+
+/// Return the indices of the characters which start a word, e.g., the `g`, `U` and `N` of
+/// `getUserName` or the `M` and `V` of `MAX_VALUE`.
+fn word_starts(chars: &[char]) -> Vec<usize> {
+    let mut starts = vec![];
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            continue;
+        }
+        let is_start = match i.checked_sub(1).map(|i| chars[i]) {
+            None => true,
+            Some(previous) if !previous.is_alphanumeric() => true,
+            Some(previous) if c.is_uppercase() && previous.is_lowercase() => true,
+            Some(previous) if c.is_numeric() != previous.is_numeric() => true,
+            // NOTE: the last capital of a run starts the next word, e.g., the `S` of `HTTPServer`.
+            Some(previous) => {
+                c.is_uppercase()
+                    && previous.is_uppercase()
+                    && chars.get(i + 1).map_or(false, |next| next.is_lowercase())
+            },
+        };
+        if is_start {
+            starts.push(i);
+        }
+    }
+    starts
+}
+
+/// Whether the query can be split into non-empty prefixes of successive words of the name, where
+/// the first prefix belongs to the first word.
+fn humps(query: &[String], folded: &[String], starts: &[usize], word: usize) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+    let candidates = if word == 0 { &starts[.. starts.len().min(1)] } else { &starts[word ..] };
+    candidates.iter().enumerate().any(|(offset, &start)| {
+        let end = starts.get(word + offset + 1).copied().unwrap_or(folded.len());
+        let length = (0 .. query.len().min(end - start))
+            .take_while(|&i| query[i] == folded[start + i])
+            .count();
+        (1 ..= length)
+            .rev()
+            .any(|taken| humps(&query[taken ..], folded, starts, word + offset + 1))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::score;
+
+    #[test]
+    fn tiers() {
+        let exact = score("getusername", "getUserName").unwrap();
+        let prefix = score("getUser", "getUserName").unwrap();
+        let humps = score("gun", "getUserName").unwrap();
+        let substring = score("username", "getUserName").unwrap();
+        let subsequence = score("gtnm", "getUserName").unwrap();
+        assert!(exact > prefix);
+        assert!(prefix > humps);
+        assert!(humps > substring);
+        assert!(substring > subsequence);
+        assert_eq!(score("xyz", "getUserName"), None);
+        assert_eq!(score("", "getUserName"), Some(0));
+    }
+
+    #[test]
+    fn camel_humps() {
+        assert_eq!(score("gUN", "getUserName"), score("gun", "getUserName"));
+        assert_eq!(score("geusna", "getUserName"), Some(3000));
+        assert_eq!(score("hs", "HTTPServer"), Some(3000));
+        assert_eq!(score("mv", "MAX_VALUE"), Some(3000));
+        assert_ne!(score("un", "getUserName"), Some(3000));
+    }
+}
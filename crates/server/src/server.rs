@@ -2,12 +2,94 @@ use std::sync::Arc;
 use tower_lsp::{jsonrpc, lsp_types::*, LanguageServer};
 
 pub fn capabilities() -> lsp::ServerCapabilities {
+    let call_hierarchy_provider = Some(lsp::CallHierarchyServerCapability::Simple(true));
+
+    let code_action_provider = {
+        let options = lsp::CodeActionOptions {
+            code_action_kinds: Some(vec![
+                lsp::CodeActionKind::QUICKFIX,
+                lsp::CodeActionKind::REFACTOR_EXTRACT,
+                lsp::CodeActionKind::REFACTOR_INLINE,
+                lsp::CodeActionKind::REFACTOR_REWRITE,
+                lsp::CodeActionKind::SOURCE_FIX_ALL,
+            ]),
+            resolve_provider: Some(true),
+            ..Default::default()
+        };
+        Some(lsp::CodeActionProviderCapability::Options(options))
+    };
+
+    let code_lens_provider = Some(lsp::CodeLensOptions {
+        resolve_provider: Some(true),
+    });
+
+    let completion_provider = Some(lsp::CompletionOptions {
+        resolve_provider: Some(true),
+        ..Default::default()
+    });
+
+    let declaration_provider = Some(lsp::DeclarationCapability::Simple(true));
+
+    let definition_provider = Some(lsp::OneOf::Left(true));
+
+    let document_formatting_provider = Some(lsp::OneOf::Left(true));
+
+    let document_highlight_provider = Some(lsp::OneOf::Left(true));
+
+    let document_on_type_formatting_provider = Some(lsp::DocumentOnTypeFormattingOptions {
+        first_trigger_character: "}".into(),
+        more_trigger_character: Some(vec![";".into(), "\n".into()]),
+    });
+
+    let document_range_formatting_provider = Some(lsp::OneOf::Left(true));
+
     let document_symbol_provider = Some(lsp::OneOf::Left(true));
 
+    let folding_range_provider = Some(lsp::FoldingRangeProviderCapability::Simple(true));
+
+    let hover_provider = Some(lsp::HoverProviderCapability::Simple(true));
+
+    let inlay_hint_provider = {
+        let options = lsp::InlayHintOptions {
+            resolve_provider: Some(true),
+            ..Default::default()
+        };
+        Some(lsp::OneOf::Right(lsp::InlayHintServerCapabilities::Options(options)))
+    };
+
+    let references_provider = Some(lsp::OneOf::Left(true));
+
+    let rename_provider = Some(lsp::OneOf::Right(lsp::RenameOptions {
+        prepare_provider: Some(true),
+        work_done_progress_options: Default::default(),
+    }));
+
+    let selection_range_provider = Some(lsp::SelectionRangeProviderCapability::Simple(true));
+
+    let semantic_tokens_provider = {
+        let options = lsp::SemanticTokensOptions {
+            legend: crate::provider::semantic_tokens::legend(),
+            range: Some(true),
+            full: Some(lsp::SemanticTokensFullOptions::Delta { delta: Some(true) }),
+            ..Default::default()
+        };
+        Some(lsp::SemanticTokensServerCapabilities::SemanticTokensOptions(options))
+    };
+
+    let signature_help_provider = Some(lsp::SignatureHelpOptions {
+        trigger_characters: Some(vec!["(".into(), ",".into()]),
+        ..Default::default()
+    });
+
+    let workspace_symbol_provider = Some(lsp::OneOf::Right(lsp::WorkspaceSymbolOptions {
+        resolve_provider: Some(true),
+        work_done_progress_options: Default::default(),
+    }));
+
     let text_document_sync = {
         let options = lsp::TextDocumentSyncOptions {
             open_close: Some(true),
-            change: Some(lsp::TextDocumentSyncKind::FULL),
+            change: Some(lsp::TextDocumentSyncKind::INCREMENTAL),
             ..Default::default()
         };
         Some(lsp::TextDocumentSyncCapability::Options(options))
@@ -15,7 +97,26 @@ pub fn capabilities() -> lsp::ServerCapabilities {
 
     lsp::ServerCapabilities {
         text_document_sync,
+        call_hierarchy_provider,
+        code_action_provider,
+        code_lens_provider,
+        completion_provider,
+        declaration_provider,
+        definition_provider,
+        document_formatting_provider,
+        document_highlight_provider,
+        document_on_type_formatting_provider,
+        document_range_formatting_provider,
         document_symbol_provider,
+        folding_range_provider,
+        hover_provider,
+        inlay_hint_provider,
+        references_provider,
+        rename_provider,
+        selection_range_provider,
+        semantic_tokens_provider,
+        signature_help_provider,
+        workspace_symbol_provider,
         ..Default::default()
     }
 }
@@ -26,17 +127,61 @@ pub struct Server {
 }
 
 impl Server {
-    pub fn new(client: tower_lsp::Client, language: tree_sitter::Language) -> Self {
-        let session = crate::core::Session::new(Some(client.clone()), language);
+    pub fn new(
+        client: tower_lsp::Client,
+        language: tree_sitter::Language,
+        syntax_tree_sink: Option<Box<dyn crate::core::syntax::SyntaxTreeSink>>,
+    ) -> Self {
+        let session = crate::core::Session::new(Some(client.clone()), language, syntax_tree_sink);
         Server { client, session }
     }
+
+    /// Build the service for the server, shared by the browser and native front ends. The syntax
+    /// tree of every parsed document is passed to the sink, if any.
+    pub fn service(
+        language: tree_sitter::Language,
+        syntax_tree_sink: Option<Box<dyn crate::core::syntax::SyntaxTreeSink>>,
+    ) -> (tower_lsp::LspService<Self>, tower_lsp::ClientSocket) {
+        tower_lsp::LspService::build(|client| Server::new(client, language, syntax_tree_sink))
+            .custom_method("textDocument/inlayHint", Server::inlay_hint)
+            .custom_method("inlayHint/resolve", Server::inlay_hint_resolve)
+            .custom_method("workspaceSymbol/resolve", Server::symbol_resolve)
+            .finish()
+    }
+
+    // NOTE: inlay hints and workspace symbol resolution are not part of the `LanguageServer` trait,
+    // so they are registered as custom methods when building the service.
+
+    pub async fn inlay_hint(&self, params: lsp::InlayHintParams) -> jsonrpc::Result<Option<Vec<lsp::InlayHint>>> {
+        crate::trace("server::inlay_hint");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::inlay_hint(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    pub async fn inlay_hint_resolve(&self, params: lsp::InlayHint) -> jsonrpc::Result<lsp::InlayHint> {
+        crate::trace("server::inlay_hint_resolve");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::inlay_hint_resolve(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    pub async fn symbol_resolve(&self, params: lsp::WorkspaceSymbol) -> jsonrpc::Result<lsp::WorkspaceSymbol> {
+        crate::trace("server::symbol_resolve");
+        let session = self.session.clone();
+        let result = crate::handler::workspace::symbol_resolve(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Server {
     async fn initialize(&self, params: InitializeParams) -> jsonrpc::Result<InitializeResult> {
-        web_sys::console::log_1(&"server::initialize".into());
+        crate::trace("server::initialize");
         *self.session.client_capabilities.write().await = Some(params.capabilities);
+        if let Some(options) = &params.initialization_options {
+            self.session.settings.write().await.update(options);
+        }
         let capabilities = capabilities();
         Ok(InitializeResult {
             capabilities,
@@ -45,44 +190,283 @@ impl LanguageServer for Server {
     }
 
     async fn initialized(&self, _: lsp::InitializedParams) {
-        web_sys::console::log_1(&"server::initialized".into());
+        crate::trace("server::initialized");
         let typ = lsp::MessageType::INFO;
         let message = "demo language server initialized!";
         self.client.log_message(typ, message).await;
     }
 
     async fn shutdown(&self) -> jsonrpc::Result<()> {
-        web_sys::console::log_1(&"server::shutdown".into());
+        crate::trace("server::shutdown");
         Ok(())
     }
 
     // FIXME: for some reason this doesn't trigger
     async fn did_open(&self, params: lsp::DidOpenTextDocumentParams) {
-        web_sys::console::log_1(&"server::did_open".into());
+        crate::trace("server::did_open");
 
         let typ = lsp::MessageType::INFO;
         let message = format!("opened document: {}", params.text_document.uri.as_str());
         self.client.log_message(typ, message).await;
 
         let session = self.session.clone();
-        crate::handler::text_document::did_open(session, params).await.unwrap();
+        if let Err(error) = crate::handler::text_document::did_open(session, params).await {
+            log::error!("'textDocument/didOpen' failed :: error: {:#?}", error);
+        }
     }
 
     async fn did_change(&self, params: lsp::DidChangeTextDocumentParams) {
-        web_sys::console::log_1(&"server::did_change".into());
+        crate::trace("server::did_change");
+        let session = self.session.clone();
+        if let Err(error) = crate::handler::text_document::did_change(session, params).await {
+            log::error!("'textDocument/didChange' failed :: error: {:#?}", error);
+        }
+    }
+
+    async fn did_close(&self, params: lsp::DidCloseTextDocumentParams) {
+        crate::trace("server::did_close");
+        let session = self.session.clone();
+        if let Err(error) = crate::handler::text_document::did_close(session, params).await {
+            log::error!("'textDocument/didClose' failed :: error: {:#?}", error);
+        }
+    }
+
+    async fn did_change_configuration(&self, params: lsp::DidChangeConfigurationParams) {
+        crate::trace("server::did_change_configuration");
+        let session = self.session.clone();
+        if let Err(error) = crate::handler::workspace::did_change_configuration(session, params).await {
+            log::error!("'workspace/didChangeConfiguration' failed :: error: {:#?}", error);
+        }
+    }
+
+    async fn goto_declaration(
+        &self,
+        params: lsp::request::GotoDeclarationParams,
+    ) -> jsonrpc::Result<Option<lsp::request::GotoDeclarationResponse>> {
+        crate::trace("server::goto_declaration");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::goto_declaration(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn goto_definition(
+        &self,
+        params: lsp::GotoDefinitionParams,
+    ) -> jsonrpc::Result<Option<lsp::GotoDefinitionResponse>> {
+        crate::trace("server::goto_definition");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::goto_definition(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn references(&self, params: lsp::ReferenceParams) -> jsonrpc::Result<Option<Vec<lsp::Location>>> {
+        crate::trace("server::references");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::references(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn document_highlight(
+        &self,
+        params: lsp::DocumentHighlightParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::DocumentHighlight>>> {
+        crate::trace("server::document_highlight");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::document_highlight(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn completion(&self, params: lsp::CompletionParams) -> jsonrpc::Result<Option<lsp::CompletionResponse>> {
+        crate::trace("server::completion");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::completion(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn completion_resolve(&self, params: lsp::CompletionItem) -> jsonrpc::Result<lsp::CompletionItem> {
+        crate::trace("server::completion_resolve");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::completion_resolve(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn hover(&self, params: lsp::HoverParams) -> jsonrpc::Result<Option<lsp::Hover>> {
+        crate::trace("server::hover");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::hover(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn signature_help(&self, params: lsp::SignatureHelpParams) -> jsonrpc::Result<Option<lsp::SignatureHelp>> {
+        crate::trace("server::signature_help");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::signature_help(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn prepare_call_hierarchy(
+        &self,
+        params: lsp::CallHierarchyPrepareParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::CallHierarchyItem>>> {
+        crate::trace("server::prepare_call_hierarchy");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::prepare_call_hierarchy(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: lsp::CallHierarchyIncomingCallsParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::CallHierarchyIncomingCall>>> {
+        crate::trace("server::incoming_calls");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::incoming_calls(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: lsp::CallHierarchyOutgoingCallsParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::CallHierarchyOutgoingCall>>> {
+        crate::trace("server::outgoing_calls");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::outgoing_calls(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn code_action(&self, params: lsp::CodeActionParams) -> jsonrpc::Result<Option<lsp::CodeActionResponse>> {
+        crate::trace("server::code_action");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::code_action(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn code_action_resolve(&self, params: lsp::CodeAction) -> jsonrpc::Result<lsp::CodeAction> {
+        crate::trace("server::code_action_resolve");
         let session = self.session.clone();
-        crate::handler::text_document::did_change(session, params)
-            .await
-            .unwrap();
+        let result = crate::handler::text_document::code_action_resolve(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn code_lens(&self, params: lsp::CodeLensParams) -> jsonrpc::Result<Option<Vec<lsp::CodeLens>>> {
+        crate::trace("server::code_lens");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::code_lens(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn code_lens_resolve(&self, params: lsp::CodeLens) -> jsonrpc::Result<lsp::CodeLens> {
+        crate::trace("server::code_lens_resolve");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::code_lens_resolve(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
     }
 
     async fn document_symbol(
         &self,
         params: lsp::DocumentSymbolParams,
     ) -> jsonrpc::Result<Option<lsp::DocumentSymbolResponse>> {
-        web_sys::console::log_1(&"server::document_symbol".into());
+        crate::trace("server::document_symbol");
         let session = self.session.clone();
         let result = crate::handler::text_document::document_symbol(session, params).await;
         Ok(result.map_err(crate::core::IntoJsonRpcError)?)
     }
+
+    async fn symbol(&self, params: lsp::WorkspaceSymbolParams) -> jsonrpc::Result<Option<Vec<lsp::SymbolInformation>>> {
+        crate::trace("server::symbol");
+        let session = self.session.clone();
+        let result = crate::handler::workspace::symbol(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: lsp::TextDocumentPositionParams,
+    ) -> jsonrpc::Result<Option<lsp::PrepareRenameResponse>> {
+        crate::trace("server::prepare_rename");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::prepare_rename(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn rename(&self, params: lsp::RenameParams) -> jsonrpc::Result<Option<lsp::WorkspaceEdit>> {
+        crate::trace("server::rename");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::rename(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn formatting(&self, params: lsp::DocumentFormattingParams) -> jsonrpc::Result<Option<Vec<lsp::TextEdit>>> {
+        crate::trace("server::formatting");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::formatting(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn range_formatting(
+        &self,
+        params: lsp::DocumentRangeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::TextEdit>>> {
+        crate::trace("server::range_formatting");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::range_formatting(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn on_type_formatting(
+        &self,
+        params: lsp::DocumentOnTypeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::TextEdit>>> {
+        crate::trace("server::on_type_formatting");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::on_type_formatting(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn folding_range(&self, params: lsp::FoldingRangeParams) -> jsonrpc::Result<Option<Vec<lsp::FoldingRange>>> {
+        crate::trace("server::folding_range");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::folding_range(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn selection_range(
+        &self,
+        params: lsp::SelectionRangeParams,
+    ) -> jsonrpc::Result<Option<Vec<lsp::SelectionRange>>> {
+        crate::trace("server::selection_range");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::selection_range(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: lsp::SemanticTokensParams,
+    ) -> jsonrpc::Result<Option<lsp::SemanticTokensResult>> {
+        crate::trace("server::semantic_tokens_full");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::semantic_tokens_full(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: lsp::SemanticTokensDeltaParams,
+    ) -> jsonrpc::Result<Option<lsp::SemanticTokensFullDeltaResult>> {
+        crate::trace("server::semantic_tokens_full_delta");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::semantic_tokens_full_delta(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: lsp::SemanticTokensRangeParams,
+    ) -> jsonrpc::Result<Option<lsp::SemanticTokensRangeResult>> {
+        crate::trace("server::semantic_tokens_range");
+        let session = self.session.clone();
+        let result = crate::handler::text_document::semantic_tokens_range(session, params).await;
+        Ok(result.map_err(crate::core::IntoJsonRpcError)?)
+    }
 }
@@ -1,4 +1,3 @@
-import debounce from "debounce";
 import * as monaco from "monaco-editor-core";
 import { MonacoToProtocolConverter } from "monaco-languageclient";
 import * as proto from "vscode-languageserver-protocol";
@@ -44,23 +43,19 @@ class Qux {}
 
     const model = monaco.editor.createModel(value, id, uri);
 
-    model.onDidChangeContent(
-      debounce(() => {
-        const text = model.getValue();
-        client.notify(proto.DidChangeTextDocumentNotification.type.method, {
-          textDocument: {
-            version: 0,
-            uri: model.uri.toString(),
-          },
-          contentChanges: [
-            {
-              range: monacoToProtocol.asRange(model.getFullModelRange()),
-              text,
-            },
-          ],
-        } as proto.DidChangeTextDocumentParams);
-      }, 200),
-    );
+    model.onDidChangeContent((event) => {
+      client.notify(proto.DidChangeTextDocumentNotification.type.method, {
+        textDocument: {
+          version: event.versionId,
+          uri: model.uri.toString(),
+        },
+        contentChanges: event.changes.map((change) => ({
+          range: monacoToProtocol.asRange(change.range),
+          rangeLength: change.rangeLength,
+          text: change.text,
+        })),
+      } as proto.DidChangeTextDocumentParams);
+    });
 
     // eslint-disable-next-line @typescript-eslint/require-await
     client.pushAfterInitializeHook(async () => {
@@ -68,7 +63,7 @@ class Qux {}
         textDocument: {
           uri: model.uri.toString(),
           languageId: language.id,
-          version: 0,
+          version: model.getVersionId(),
           text: model.getValue(),
         },
       } as proto.DidOpenTextDocumentParams);
@@ -84,6 +79,7 @@ class Qux {}
     monaco.editor.create(container, {
       model,
       automaticLayout: true,
+      formatOnType: true,
     });
   }
 
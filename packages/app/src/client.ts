@@ -58,7 +58,30 @@ export default class Client extends jsrpc.JSONRPCServerAndClient {
       clientInfo: {
         name: "demo-language-client",
       },
-      capabilities: {},
+      capabilities: {
+        textDocument: {
+          codeAction: {
+            dataSupport: true,
+            resolveSupport: { properties: ["edit"] },
+          },
+          completion: {
+            completionItem: {
+              documentationFormat: [proto.MarkupKind.Markdown, proto.MarkupKind.PlainText],
+              snippetSupport: true,
+            },
+          },
+          hover: { contentFormat: [proto.MarkupKind.Markdown, proto.MarkupKind.PlainText] },
+          signatureHelp: {
+            signatureInformation: { documentationFormat: [proto.MarkupKind.Markdown, proto.MarkupKind.PlainText] },
+          },
+        },
+        workspace: {
+          codeLens: { refreshSupport: true },
+        },
+      },
+      initializationOptions: {
+        inlayHints: { parameterNames: "literals", destructuredDefaults: true, arrayIndices: true },
+      },
       rootUri: null,
     } as proto.InitializeParams) as Promise<jsrpc.JSONRPCResponse>);
 
@@ -40,6 +40,15 @@ export default class Language implements monaco.languages.ILanguageExtensionPoin
   private registerLanguage(client: Client): void {
     void client;
     monaco.languages.register(Language.extensionPoint());
+    // process "textDocument/publishDiagnostics": client <- server
+    client.addMethod(proto.PublishDiagnosticsNotification.type.method, (params) => {
+      const { uri, diagnostics } = params as proto.PublishDiagnosticsParams;
+      const model = monaco.editor.getModel(monaco.Uri.parse(uri));
+      if (null != model) {
+        monaco.editor.setModelMarkers(model, this.id, protocolToMonaco.asDiagnostics(diagnostics));
+      }
+      return;
+    });
     monaco.languages.registerDocumentSymbolProvider(this.id, {
       // eslint-disable-next-line
       async provideDocumentSymbols(model, token): Promise<monaco.languages.DocumentSymbol[]> {
@@ -56,6 +65,265 @@ export default class Language implements monaco.languages.ILanguageExtensionPoin
         return result;
       },
     });
+    // NOTE: the converted actions lose their `data`, which the server needs to resolve them.
+    const codeActions = new WeakMap<monaco.languages.CodeAction, proto.CodeAction>();
+    monaco.languages.registerCodeActionProvider(this.id, {
+      // eslint-disable-next-line
+      async provideCodeActions(model, range, context, token): Promise<monaco.languages.CodeActionList> {
+        void token;
+        const response = await (client.request(proto.CodeActionRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          range: monacoToProtocol.asRange(range),
+          context: {
+            diagnostics: monacoToProtocol.asDiagnostics(context.markers),
+            only: context.only ? [context.only] : undefined,
+          },
+        } as proto.CodeActionParams) as Promise<(proto.Command | proto.CodeAction)[] | null>);
+
+        const list = protocolToMonaco.asCodeActionList(response ?? []);
+        (response ?? []).forEach((action, index) => {
+          if (proto.CodeAction.is(action)) {
+            codeActions.set(list.actions[index], action);
+          }
+        });
+        return list;
+      },
+      // eslint-disable-next-line
+      async resolveCodeAction(codeAction, token): Promise<monaco.languages.CodeAction> {
+        void token;
+        const action = codeActions.get(codeAction);
+        if (!action || action.edit) {
+          return codeAction;
+        }
+        const response = await (client.request(
+          proto.CodeActionResolveRequest.type.method,
+          action,
+        ) as Promise<proto.CodeAction>);
+
+        if (response.edit) {
+          codeAction.edit = protocolToMonaco.asWorkspaceEdit(response.edit);
+        }
+        return codeAction;
+      },
+    });
+    // NOTE: the converted lenses lose their `data`, which the server needs to resolve them.
+    const codeLenses = new WeakMap<monaco.languages.CodeLens, proto.CodeLens>();
+    const codeLensesChanged = new monaco.Emitter<monaco.languages.CodeLensProvider>();
+    const codeLensProvider: monaco.languages.CodeLensProvider = {
+      onDidChange: codeLensesChanged.event,
+      // eslint-disable-next-line
+      async provideCodeLenses(model, token): Promise<monaco.languages.CodeLensList> {
+        void token;
+        const response = await (client.request(proto.CodeLensRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+        } as proto.CodeLensParams) as Promise<proto.CodeLens[] | null>);
+
+        const lenses = (response ?? []).map((lens) => {
+          const codeLens = protocolToMonaco.asCodeLens(lens);
+          codeLenses.set(codeLens, lens);
+          return codeLens;
+        });
+        // eslint-disable-next-line @typescript-eslint/no-empty-function
+        return { lenses, dispose: () => {} };
+      },
+      // eslint-disable-next-line
+      async resolveCodeLens(model, codeLens, token): Promise<monaco.languages.CodeLens> {
+        void model;
+        void token;
+        const lens = codeLenses.get(codeLens);
+        if (!lens) {
+          return codeLens;
+        }
+        const response = await (client.request(
+          proto.CodeLensResolveRequest.type.method,
+          lens,
+        ) as Promise<proto.CodeLens>);
+
+        const command = response.command;
+        if (command?.command === "editor.action.showReferences" && command.arguments) {
+          // NOTE: the editor command expects its arguments as editor values rather than protocol values.
+          const [uri, position, locations] = command.arguments as [string, proto.Position, proto.Location[]];
+          command.arguments = [
+            monaco.Uri.parse(uri),
+            protocolToMonaco.asPosition(position),
+            locations.map((location) => protocolToMonaco.asLocation(location)),
+          ];
+        }
+        return { ...codeLens, command: command && protocolToMonaco.asCommand(command) };
+      },
+    };
+    monaco.languages.registerCodeLensProvider(this.id, codeLensProvider);
+    // process "workspace/codeLens/refresh": client <- server
+    client.addMethod(proto.CodeLensRefreshRequest.type.method, () => {
+      codeLensesChanged.fire(codeLensProvider);
+      return null;
+    });
+    // NOTE: the converted hints lose their `data`, which the server needs to resolve them.
+    const inlayHints = new WeakMap<monaco.languages.InlayHint, proto.InlayHint>();
+    const asTooltip = (tooltip: proto.InlayHint["tooltip"]): undefined | string | monaco.IMarkdownString => {
+      if (tooltip === undefined || typeof tooltip === "string") {
+        return tooltip;
+      }
+      return tooltip.kind === proto.MarkupKind.Markdown ? { value: tooltip.value } : tooltip.value;
+    };
+    const asInlayHint = (hint: proto.InlayHint): monaco.languages.InlayHint => ({
+      label: typeof hint.label === "string" ? hint.label : hint.label.map((part) => part.value).join(""),
+      tooltip: asTooltip(hint.tooltip),
+      position: protocolToMonaco.asPosition(hint.position),
+      kind:
+        hint.kind === proto.InlayHintKind.Parameter
+          ? monaco.languages.InlayHintKind.Parameter
+          : monaco.languages.InlayHintKind.Type,
+      paddingLeft: hint.paddingLeft,
+      paddingRight: hint.paddingRight,
+    });
+    monaco.languages.registerInlayHintsProvider(this.id, {
+      // eslint-disable-next-line
+      async provideInlayHints(model, range, token): Promise<monaco.languages.InlayHintList> {
+        void token;
+        const response = await (client.request(proto.InlayHintRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          range: monacoToProtocol.asRange(range),
+        } as proto.InlayHintParams) as Promise<proto.InlayHint[] | null>);
+
+        const hints = (response ?? []).map((hint) => {
+          const inlayHint = asInlayHint(hint);
+          inlayHints.set(inlayHint, hint);
+          return inlayHint;
+        });
+        // eslint-disable-next-line @typescript-eslint/no-empty-function
+        return { hints, dispose: () => {} };
+      },
+      // eslint-disable-next-line
+      async resolveInlayHint(inlayHint, token): Promise<monaco.languages.InlayHint> {
+        void token;
+        const hint = inlayHints.get(inlayHint);
+        if (!hint || !hint.data) {
+          return inlayHint;
+        }
+        const response = await (client.request(
+          proto.InlayHintResolveRequest.type.method,
+          hint,
+        ) as Promise<proto.InlayHint>);
+
+        return { ...inlayHint, tooltip: asTooltip(response.tooltip) };
+      },
+    });
+    monaco.languages.registerCompletionItemProvider(this.id, {
+      // eslint-disable-next-line
+      async provideCompletionItems(model, position, context, token): Promise<monaco.languages.CompletionList> {
+        void context;
+        void token;
+        const response = await (client.request(proto.CompletionRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          position: monacoToProtocol.asPosition(position.lineNumber, position.column),
+        } as proto.CompletionParams) as Promise<proto.CompletionItem[] | null>);
+
+        const word = model.getWordUntilPosition(position);
+        const range = new monaco.Range(position.lineNumber, word.startColumn, position.lineNumber, word.endColumn);
+        return protocolToMonaco.asCompletionResult(response, range);
+      },
+      // eslint-disable-next-line
+      async resolveCompletionItem(item, token): Promise<monaco.languages.CompletionItem> {
+        void token;
+        const response = await (client.request(
+          proto.CompletionResolveRequest.type.method,
+          monacoToProtocol.asCompletionItem(item),
+        ) as Promise<proto.CompletionItem>);
+
+        return protocolToMonaco.asCompletionItem(response, item.range);
+      },
+    });
+    const asFormattingOptions = (options: monaco.languages.FormattingOptions): proto.FormattingOptions => ({
+      tabSize: options.tabSize,
+      insertSpaces: options.insertSpaces,
+      trimTrailingWhitespace: true,
+      insertFinalNewline: true,
+    });
+    monaco.languages.registerDocumentFormattingEditProvider(this.id, {
+      // eslint-disable-next-line
+      async provideDocumentFormattingEdits(model, options, token): Promise<monaco.languages.TextEdit[]> {
+        void token;
+        const response = await (client.request(proto.DocumentFormattingRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          options: asFormattingOptions(options),
+        } as proto.DocumentFormattingParams) as Promise<proto.TextEdit[] | null>);
+
+        return protocolToMonaco.asTextEdits(response) ?? [];
+      },
+    });
+    monaco.languages.registerDocumentRangeFormattingEditProvider(this.id, {
+      // eslint-disable-next-line
+      async provideDocumentRangeFormattingEdits(model, range, options, token): Promise<monaco.languages.TextEdit[]> {
+        void token;
+        const response = await (client.request(proto.DocumentRangeFormattingRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          range: monacoToProtocol.asRange(range),
+          options: asFormattingOptions(options),
+        } as proto.DocumentRangeFormattingParams) as Promise<proto.TextEdit[] | null>);
+
+        return protocolToMonaco.asTextEdits(response) ?? [];
+      },
+    });
+    monaco.languages.registerOnTypeFormattingEditProvider(this.id, {
+      autoFormatTriggerCharacters: ["}", ";", "\n"],
+      // eslint-disable-next-line
+      async provideOnTypeFormattingEdits(model, position, ch, options, token): Promise<monaco.languages.TextEdit[]> {
+        void token;
+        const response = await (client.request(proto.DocumentOnTypeFormattingRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          position: monacoToProtocol.asPosition(position.lineNumber, position.column),
+          ch,
+          options: asFormattingOptions(options),
+        } as proto.DocumentOnTypeFormattingParams) as Promise<proto.TextEdit[] | null>);
+
+        return protocolToMonaco.asTextEdits(response) ?? [];
+      },
+    });
+    monaco.languages.registerHoverProvider(this.id, {
+      // eslint-disable-next-line
+      async provideHover(model, position, token): Promise<monaco.languages.Hover | undefined> {
+        void token;
+        const response = await (client.request(proto.HoverRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          position: monacoToProtocol.asPosition(position.lineNumber, position.column),
+        } as proto.HoverParams) as Promise<proto.Hover | null>);
+
+        return protocolToMonaco.asHover(response) ?? undefined;
+      },
+    });
+    monaco.languages.registerSignatureHelpProvider(this.id, {
+      signatureHelpTriggerCharacters: ["(", ","],
+      // eslint-disable-next-line
+      async provideSignatureHelp(model, position, token, context): Promise<monaco.languages.SignatureHelpResult> {
+        void token;
+        void context;
+        const response = await (client.request(proto.SignatureHelpRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          position: monacoToProtocol.asPosition(position.lineNumber, position.column),
+        } as proto.SignatureHelpParams) as Promise<proto.SignatureHelp | null>);
+
+        return protocolToMonaco.asSignatureHelpResult(response);
+      },
+    });
+    monaco.languages.registerSelectionRangeProvider(this.id, {
+      // eslint-disable-next-line
+      async provideSelectionRanges(model, positions, token): Promise<monaco.languages.SelectionRange[][]> {
+        void token;
+        const response = await (client.request(proto.SelectionRangeRequest.type.method, {
+          textDocument: monacoToProtocol.asTextDocumentIdentifier(model),
+          positions: positions.map((position) => monacoToProtocol.asPosition(position.lineNumber, position.column)),
+        } as proto.SelectionRangeParams) as Promise<proto.SelectionRange[] | null>);
+
+        return (response ?? []).map((selectionRange) => {
+          const result: monaco.languages.SelectionRange[] = [];
+          for (let current: proto.SelectionRange | undefined = selectionRange; current; current = current.parent) {
+            result.push({ range: protocolToMonaco.asRange(current.range) });
+          }
+          return result;
+        });
+      },
+    });
   }
 
   static initialize(client: Client): Language {